@@ -0,0 +1,24 @@
+#!/bin/sh
+# src/lib.rs と src/main.rs を提出用の 1 ファイルにまとめる
+# usage: ./bundle.sh [出力先 (既定: target/bundle/main.rs)]
+set -eu
+cd "$(dirname "$0")"
+
+out=${1:-target/bundle/main.rs}
+mkdir -p "$(dirname "$out")"
+
+# main.rs は `use ahc033::*;` 以外で lib を参照しない前提
+if grep -v '^use ahc033::\*;$' src/main.rs | grep -q 'ahc033::'; then
+    echo "error: src/main.rs refers to ahc033:: other than \`use ahc033::*;\`" >&2
+    exit 1
+fi
+
+{
+    # 提出環境では feature "verify" が定義されないので、その cfg の警告を抑える
+    echo '#![allow(unexpected_cfgs)]'
+    cat src/lib.rs
+    echo
+    grep -v '^use ahc033::\*;$' src/main.rs
+} > "$out"
+
+echo "bundled: $out" >&2
@@ -0,0 +1,3610 @@
+use proconio::*;
+
+/* 全クレーンの操作列を求める関数 (返り値はクレーンごとの出力行、config は resolve 済みであること) */
+pub fn solve(input: &Input, config: &SolverConfig) -> Vec<String> {
+    debug_assert_eq!(config.crane_perm.len(), config.using_crane);
+    let start = std::time::Instant::now();
+    let ans = if config.planner {
+        plan(input, config)
+    } else {
+        beam_solve(input, config).unwrap_or_else(|partials| {
+            // turn 層・search_time 秒以内に全て搬出できなかった場合は途中の出力の続きをプランナーで計画する
+            eprintln!(
+                "beam search did not finish within {} layers or {:.2}s, completing the best partial output",
+                config.turn,
+                config.search_time()
+            );
+            // 続きの計画は complete_time 秒の分も使い、局所探索の時間は残す
+            let deadline = start
+                + std::time::Duration::from_secs_f64(config.search_time() + config.complete_time);
+            complete(input, config, &partials, deadline)
+        })
+    };
+
+    // 出力を再生しながら得点が良くなる編集を探す
+    let ans = post_optimize(input, config, ans);
+
+    // tools の判定で検証し、不正な場合は確実に合法な解に差し替える
+    #[cfg(feature = "verify")]
+    let ans = verify_output(input, ans);
+
+    ans
+}
+
+/*
+    ビームサーチで全クレーンの操作列を求める関数
+    turn 層以内に全て搬出できなかった場合は、最後の層で評価値の良い葉 PARTIAL_LEAVES 個までの途中の出力を Err で返す
+*/
+pub fn beam_solve(input: &Input, config: &SolverConfig) -> Result<Vec<String>, Vec<Vec<String>>> {
+    match config.evaluator {
+        EvaluatorKind::Potential => beam_solve_with(input, config, Potential::new(input)),
+        EvaluatorKind::ScoreAware => beam_solve_with(input, config, ScoreAware),
+    }
+}
+
+/* 評価関数 evaluator でビームサーチする関数 */
+pub fn beam_solve_with<E: Evaluator>(
+    input: &Input,
+    config: &SolverConfig,
+    evaluator: E,
+) -> Result<Vec<String>, Vec<Vec<String>>> {
+    // 残り時間に合わせてビーム幅を調整する (ターン数はコンテナ数 N^2 に比例すると見込む)
+    let expected_turn = EXPECTED_TURN * input.n * input.n / (BASE_N * BASE_N);
+    let expected_layers = if config.joint_move {
+        expected_turn
+    } else {
+        expected_turn * config.using_crane
+    };
+    let mut time_keeper = TimeKeeper::new(config, expected_layers);
+
+    /*  ========== ビームサーチ解法 ========== */
+    // BeamSearchの初期化
+    let initial_node: Node = {
+        Node {
+            op: !0,
+            parent: !0,
+            child: !0,
+            prev: !0,
+            next: !0,
+        }
+    };
+
+    // 初手動作を指定可能
+    // let mut actions: String = "PPPPPRRRRRRRRRRQQQQQ".to_string();
+    let actions: String = "".to_string();
+
+    let mut initial_terminal = Terminal::new(input, config, evaluator);
+    for (i, action) in actions.chars().enumerate() {
+        initial_terminal.prepare_cont();
+        initial_terminal.cranes[config.crane_perm[i % config.crane_perm.len()]].action(
+            OP.iter().position(|&x| x == action).unwrap(),
+            &mut initial_terminal.grid_crane,
+            &mut initial_terminal.grid_cont,
+            &mut initial_terminal.cont_suspended,
+        );
+    }
+
+    // crane_perm に含まれないクレーンは邪魔にならないように爆破
+    if !config.joint_move {
+        for i in (0..input.n).filter(|i| !config.crane_perm.contains(i)) {
+            initial_terminal.cranes[i].action(
+                Operation::Bomb as usize,
+                &mut initial_terminal.grid_crane,
+                &mut initial_terminal.grid_cont,
+                &mut initial_terminal.cont_suspended,
+            );
+        }
+    }
+
+    initial_terminal.prepare_cont();
+    initial_terminal.score = initial_terminal.evaluate();
+    initial_terminal.hash = initial_terminal.compute_hash();
+    let mut solver = BeamSearch::new(initial_terminal, initial_node, config);
+    eprintln!("initial score: {}", solver.state.score);
+    let result = match config.search {
+        SearchKind::Beam => solver.beam(config, &mut time_keeper),
+        SearchKind::Chokudai => solver.chokudai(config, &time_keeper),
+    };
+
+    // 同時操作ではクレーン順に並べている
+    let crane_perm: Vec<usize> = if config.joint_move {
+        (0..input.n).collect()
+    } else {
+        config.crane_perm.clone()
+    };
+    // ノード idx までの操作列を復元して出力にする
+    let output = |idx: usize| {
+        let mut actions = actions.clone();
+        for op in solver.restore(idx) {
+            if config.joint_move {
+                // クレーン順に並べれば to_output でそのまま各クレーンに振り分けられる
+                for a in decode_joint(op, input.n) {
+                    actions.push(OP[a]);
+                }
+            } else {
+                actions.push(OP[op]);
+            }
+        }
+        to_output(actions, &crane_perm, input.n)
+    };
+
+    match result {
+        Ok(best_idx) => Ok(output(best_idx)),
+        Err(leaves) => Err(leaves.iter().map(|&idx| output(idx)).collect()),
+    }
+}
+
+/* 1 台ずつの操作列を crane_perm の順に各クレーンへ振り分ける関数 (動かさないクレーンは最初に爆破) */
+pub fn to_output(actions: String, crane_perm: &[usize], n: usize) -> Vec<String> {
+    let mut ans: Vec<String> = vec!["".to_string(); n];
+    for (i, action) in actions.chars().enumerate() {
+        let a = &mut ans[crane_perm[i % crane_perm.len()]];
+        // 爆破したクレーンにはそれ以降の操作を出力しない
+        if !a.ends_with('B') {
+            a.push(action);
+        }
+    }
+    for a in ans.iter_mut().filter(|a| a.is_empty()) {
+        a.push('B');
+    }
+    ans
+}
+
+/* tools の判定で出力を検証し、不正な場合は確実に合法な解に差し替える関数 */
+#[cfg(feature = "verify")]
+fn verify_output(input: &Input, ans: Vec<String>) -> Vec<String> {
+    match judge(input, &ans) {
+        Ok(score) => {
+            eprintln!("verified score: {}", score);
+            ans
+        }
+        Err(err) => {
+            eprintln!("invalid output: {}", err);
+            let fallback = fallback_output(input);
+            match judge(input, &fallback) {
+                Ok(score) => {
+                    eprintln!("fallback score: {}", score);
+                    fallback
+                }
+                Err(err) => {
+                    eprintln!("invalid fallback: {}", err);
+                    ans
+                }
+            }
+        }
+    }
+}
+
+/* tools::compute_score_details で採点する関数 (不正な場合は違反したターンとクレーンを返す) */
+#[cfg(feature = "verify")]
+fn judge(input: &Input, ans: &[String]) -> Result<i64, String> {
+    let tools_input = to_tools_input(input);
+    let out = tools::parse_output(&tools_input, &ans.join("\n"))?;
+    let t = out.out.iter().map(|s| s.len()).max().unwrap();
+    let (details, err, _) = tools::compute_score_details(&tools_input, &out, t);
+    if err.is_empty() {
+        return Ok(details.score);
+    }
+
+    // 違反したターンで、操作を止めると合法になるクレーンを違反したクレーンとする
+    let turn: usize = err
+        .rsplit("(turn ")
+        .next()
+        .and_then(|s| s.trim_end_matches(')').parse().ok())
+        .unwrap();
+    let crane = (0..input.n).find(|&i| {
+        let mut out = tools::Output {
+            out: out.out.clone(),
+        };
+        match out.out[i].get_mut(turn) {
+            Some(mv) if *mv != '.' => *mv = '.',
+            _ => return false,
+        }
+        tools::compute_score_details(&tools_input, &out, turn + 1)
+            .1
+            .is_empty()
+    });
+    match crane {
+        Some(i) => Err(format!("{} [crane {}: '{}']", err, i, out.out[i][turn])),
+        None => Err(err),
+    }
+}
+
+/* 入力を tools の形式に変換する関数 */
+#[cfg(feature = "verify")]
+fn to_tools_input(input: &Input) -> tools::Input {
+    let mut text = format!("{}\n", input.n);
+    for row in input.a.iter() {
+        let row: Vec<String> = row.iter().map(|a| a.to_string()).collect();
+        text += &format!("{}\n", row.join(" "));
+    }
+    tools::parse_input(&text)
+}
+
+/*
+    出力を Terminal で再生しながら局所探索で短くする関数 (post_time 秒まで)
+    各ターンの操作は joint_legal でジャッジと同じ規則で判定し、得点は Planner::score で数える
+    得点はターン数 (最も長い行の長さ) なので、ターンを消す編集 (merge_turn) で良くなる場合だけ残す
+    1 台の操作を待機に置き換える編集 (simplify) はそれだけでは得点が変わらないので、続けてターンを消せる場合だけ残す
+*/
+fn post_optimize(input: &Input, config: &SolverConfig, ans: Vec<String>) -> Vec<String> {
+    if config.post_time <= 0.0 {
+        return ans;
+    }
+    let start = std::time::Instant::now();
+    let initial = Planner::new(initial_planner_state(input, config));
+    // 不正な出力は None
+    let score = |out: &[Vec<char>]| {
+        let mut planner = initial.clone();
+        let t = out.iter().map(|s| s.len()).max().unwrap();
+        for k in 0..t {
+            let ops = Planner::turn_ops(out, k);
+            if !planner.state.joint_legal(&ops) {
+                return None;
+            }
+            planner.step(ops);
+        }
+        Some(planner.score())
+    };
+
+    let mut best: Vec<Vec<char>> = ans.iter().map(|a| a.chars().collect()).collect();
+    let Some(initial_score) = score(&best) else {
+        // 不正な出力は verify_output に任せる
+        return ans;
+    };
+    let mut best_score = initial_score;
+    let mut tried = 0;
+    'search: loop {
+        // 今の出力そのままか、1 台分の編集をした出力から、ターンを 1 つ消して良くなるものを探す
+        let mut bases = vec![best.clone()];
+        bases.extend(simplify(&best));
+        for base in bases {
+            if base != best && score(&base).is_none() {
+                continue;
+            }
+            let len = base.iter().map(|s| s.len()).max().unwrap();
+            for t in (0..len).rev() {
+                if start.elapsed().as_secs_f64() > config.post_time {
+                    break 'search;
+                }
+                let Some(next) = merge_turn(&base, t) else {
+                    continue;
+                };
+                tried += 1;
+                if let Some(next_score) = score(&next) {
+                    if next_score < best_score {
+                        best = next;
+                        best_score = next_score;
+                        continue 'search;
+                    }
+                }
+            }
+        }
+        break;
+    }
+    eprintln!(
+        "post optimization: {} -> {} ({} tried, {:.3}s)",
+        initial_score,
+        best_score,
+        tried,
+        start.elapsed().as_secs_f64()
+    );
+    best.into_iter().map(|s| s.into_iter().collect()).collect()
+}
+
+/*
+    ターン t を消す (t 以降にも操作のあるクレーンは、t 以降の最初の待機を消して後の操作を 1 ターン早める) 関数
+    待機がないクレーンは最後の操作を消す (全て搬出した後の余分な操作を想定)
+*/
+fn merge_turn(out: &[Vec<char>], t: usize) -> Option<Vec<Vec<char>>> {
+    let mut next = out.to_vec();
+    for s in next.iter_mut().filter(|s| s.len() > t) {
+        if s.len() == 1 {
+            // 空行は出力できない
+            return None;
+        }
+        let k = (t..s.len()).find(|&k| s[k] == '.').unwrap_or(s.len() - 1);
+        s.remove(k);
+    }
+    Some(next)
+}
+
+/*
+    1 台の操作のうち、移動を待機に置き換えて減らした出力を列挙する関数
+    - 掴む・離すを挟まずに打ち消し合う移動の組を待機にする
+    - 掴む・離す (と爆破) の間の移動を、同じ地点への最短経路 (縦横どちらを先にするかの 2 通り) と待機に置き換える
+*/
+fn simplify(out: &[Vec<char>]) -> Vec<Vec<Vec<char>>> {
+    let dir = |c: char| DIR.iter().position(|&d| d == c);
+    let mut ret = vec![];
+    for (i, s) in out.iter().enumerate() {
+        let mut edit = |s: Vec<char>| {
+            let mut next = out.to_vec();
+            next[i] = s;
+            ret.push(next);
+        };
+
+        // 打ち消し合う移動の組
+        for k1 in 0..s.len() {
+            let Some(d) = dir(s[k1]) else {
+                continue;
+            };
+            let opposite = DIR[reverse_op(d) as usize];
+            let Some(k2) = (k1 + 1..s.len())
+                .take_while(|&k| s[k] == '.' || dir(s[k]).is_some())
+                .find(|&k| s[k] == opposite)
+            else {
+                continue;
+            };
+            let mut next = s.clone();
+            next[k1] = '.';
+            next[k2] = '.';
+            edit(next);
+        }
+
+        // 掴む・離すの間の遠回り
+        let mut begin = 0;
+        while begin < s.len() {
+            let end = (begin..s.len())
+                .find(|&k| s[k] != '.' && dir(s[k]).is_none())
+                .unwrap_or(s.len());
+            let (mut dx, mut dy) = (0, 0);
+            let mut moves = 0;
+            for d in s[begin..end].iter().filter_map(|&c| dir(c)) {
+                dx += DX[d];
+                dy += DY[d];
+                moves += 1;
+            }
+            if moves > dx.unsigned_abs() + dy.unsigned_abs() {
+                let vertical = vec![if dx > 0 { 'D' } else { 'U' }; dx.unsigned_abs()];
+                let horizontal = vec![if dy > 0 { 'R' } else { 'L' }; dy.unsigned_abs()];
+                for path in [
+                    [vertical.clone(), horizontal.clone()].concat(),
+                    [horizontal.clone(), vertical.clone()].concat(),
+                ] {
+                    let mut next = s.clone();
+                    for (k, c) in (begin..end).zip(path.iter().chain(std::iter::repeat(&'.'))) {
+                        next[k] = *c;
+                    }
+                    edit(next);
+                }
+            }
+            begin = end + 1;
+        }
+    }
+    ret
+}
+
+/* 小クレーンを全て爆破し、大クレーンだけで 1 つずつ運ぶ確実に合法な解を作る関数 */
+#[cfg(feature = "verify")]
+fn fallback_output(input: &Input) -> Vec<String> {
+    let n = input.n;
+    let mut sim = SimpleTerminal::new(input);
+
+    // 小クレーンは最初のターンに爆破
+    let mut ans: Vec<String> = vec!["B".to_string(); n];
+    ans[0].clear();
+
+    while sim.done < n * n && ans[0].len() < 10000 {
+        // 今すぐ搬出できるコンテナがあれば搬出口へ、無ければ搬入口のコンテナを仮置き場へ運ぶ
+        let conts: Vec<(usize, usize)> = (0..n)
+            .flat_map(|x| (0..n - 1).map(move |y| (x, y)))
+            .filter(|&(x, y)| sim.board[x][y] != -1)
+            .collect();
+        let dist = |&&(x, y): &&(usize, usize)| sim.x.abs_diff(x) + sim.y.abs_diff(y);
+        let ready = conts
+            .iter()
+            .filter(|&&(x, y)| sim.out_cont_idx.contains(&sim.board[x][y]))
+            .min_by_key(dist);
+        let gate = conts.iter().filter(|&&(_, y)| y == 0).min_by_key(dist);
+        let Some(&(x, y)) = ready.or(gate).or(conts.first()) else {
+            // 搬入待ちのコンテナしか無い場合は待つ
+            ans[0].push(sim.action('.'));
+            continue;
+        };
+
+        let cont = sim.board[x][y];
+        let out_pos = ((cont as usize) / n, n - 1);
+        let to = if ready.is_some() {
+            out_pos
+        } else {
+            // 仮置き場が無い場合は順番を無視して搬出
+            (0..n)
+                .flat_map(|x| (1..n - 1).map(move |y| (x, y)))
+                .filter(|&(tx, ty)| sim.board[tx][ty] == -1 && (tx, ty) != (x, y))
+                .min_by_key(|&(tx, ty)| tx.abs_diff(x) + ty.abs_diff(y))
+                .unwrap_or(out_pos)
+        };
+
+        for c in sim.path_to(x, y) {
+            ans[0].push(sim.action(c));
+        }
+        ans[0].push(sim.action('P'));
+        for c in sim.path_to(to.0, to.1) {
+            ans[0].push(sim.action(c));
+        }
+        ans[0].push(sim.action('Q'));
+    }
+    if ans[0].is_empty() {
+        ans[0].push('.');
+    }
+    ans
+}
+
+/* fallback_output 用の、小クレーンが爆破済みの盤面を tools::State::apply と同じ規則で動かす構造体 */
+#[cfg(feature = "verify")]
+struct SimpleTerminal {
+    n: usize,
+    x: usize,
+    y: usize,
+    done: usize,
+    holding: i64,
+    conts: Vec<Vec<i64>>,
+    board: Vec<Vec<i64>>,
+    out_cont_idx: Vec<i64>,
+    incoming_cont_idx: Vec<usize>,
+}
+#[cfg(feature = "verify")]
+impl SimpleTerminal {
+    fn new(input: &Input) -> Self {
+        let n = input.n;
+        let mut board = vec![vec![-1; n]; n];
+        for (row, a) in board.iter_mut().zip(input.a.iter()) {
+            row[0] = a[0];
+        }
+        Self {
+            n,
+            x: 0,
+            y: 0,
+            done: 0,
+            holding: -1,
+            conts: input.a.to_vec(),
+            board,
+            out_cont_idx: (0..n).map(|i| (i * n) as i64).collect(),
+            incoming_cont_idx: vec![1; n],
+        }
+    }
+
+    /* (x, y) までの移動の操作列 */
+    fn path_to(&self, x: usize, y: usize) -> Vec<char> {
+        let mut path = vec![];
+        path.extend(std::iter::repeat_n(
+            if x > self.x { 'D' } else { 'U' },
+            x.abs_diff(self.x),
+        ));
+        path.extend(std::iter::repeat_n(
+            if y > self.y { 'R' } else { 'L' },
+            y.abs_diff(self.y),
+        ));
+        path
+    }
+
+    /* 大クレーンの操作を 1 ターン分適用し、搬入・搬出を行う */
+    fn action(&mut self, c: char) -> char {
+        match c {
+            'P' => std::mem::swap(&mut self.holding, &mut self.board[self.x][self.y]),
+            'Q' => std::mem::swap(&mut self.holding, &mut self.board[self.x][self.y]),
+            '.' => (),
+            _ => {
+                let dir = DIR.iter().position(|&d| d == c).unwrap();
+                self.x = (self.x as isize + DX[dir]) as usize;
+                self.y = (self.y as isize + DY[dir]) as usize;
+            }
+        }
+        for i in 0..self.n {
+            if self.board[i][0] == -1
+                && self.incoming_cont_idx[i] < self.n
+                && (self.holding == -1 || (self.x, self.y) != (i, 0))
+            {
+                self.board[i][0] = self.conts[i][self.incoming_cont_idx[i]];
+                self.incoming_cont_idx[i] += 1;
+            }
+            let cont = self.board[i][self.n - 1];
+            if cont != -1 {
+                if let Some(idx) = self.out_cont_idx.iter_mut().find(|idx| **idx == cont) {
+                    *idx += 1;
+                }
+                self.board[i][self.n - 1] = -1;
+                self.done += 1;
+            }
+        }
+        c
+    }
+}
+
+pub const DIR_NUM: usize = 4;
+pub const DX: [isize; DIR_NUM] = [0, 1, 0, -1];
+pub const DY: [isize; DIR_NUM] = [1, 0, -1, 0];
+pub const DIR: [char; DIR_NUM] = ['R', 'D', 'L', 'U'];
+
+pub const OP_NUM: usize = 8;
+pub const OP: [char; OP_NUM] = ['R', 'D', 'L', 'U', 'P', 'Q', '.', 'B'];
+
+// 同時操作 (SolverConfig::joint_move) での Node の op は全クレーン分の操作
+pub const JOINT_CANDS: usize = 8; // 1 ノードから生成する同時操作の候補数
+pub const INITIAL_NODE_LAYERS: usize = 8; // ビーム幅の何倍のノードを最初に確保するか
+pub const PARTIAL_LEAVES: usize = 8; // ビームサーチが終わらなかった場合に続きを計画する候補にする葉の数
+pub const HOLD_PENALTY: i64 = 2; // 同時操作で小クレーンが搬出できないコンテナを持つことへのペナルティ倍率
+pub const OP_BITS: usize = 3; // 同時操作で 1 クレーン分の操作に使うビット数
+
+pub const BASE_N: usize = 5; // 本番の N (各定数はこの N に合わせている)
+pub const EXPECTED_TURN: usize = 120; // N = BASE_N で全て搬出するまでの実ターン数の見込み
+
+pub enum Operation {
+    Right,
+    Down,
+    Left,
+    Up,
+    Suspend,
+    Lower,
+    Stop,
+    Bomb,
+}
+
+#[inline]
+/* 反対の方向を返す関数 */
+pub fn reverse_op(op: usize) -> Operation {
+    match op {
+        0 => Operation::Left,
+        1 => Operation::Up,
+        2 => Operation::Right,
+        3 => Operation::Down,
+        4 => Operation::Lower,
+        5 => Operation::Suspend,
+        6 => Operation::Stop,
+        7 => Operation::Bomb,
+        _ => panic!("invalid op"),
+    }
+}
+
+/* 全クレーン分の操作を 1 つの op にまとめる関数 */
+pub fn encode_joint(ops: &[usize]) -> usize {
+    ops.iter().rev().fold(0, |acc, &op| (acc << OP_BITS) | op)
+}
+
+/* encode_joint でまとめた op を全クレーン分の操作に戻す関数 */
+pub fn decode_joint(op: usize, n: usize) -> Vec<usize> {
+    (0..n)
+        .map(|i| (op >> (i * OP_BITS)) & ((1 << OP_BITS) - 1))
+        .collect()
+}
+
+#[inline]
+pub fn out_field(x: isize, y: isize, h: isize, w: isize) -> bool {
+    !(0 <= x && x < h && 0 <= y && y < w)
+}
+
+/*
+    ソルバーの実行時パラメータ
+    既定値 < 設定ファイル < 環境変数 < コマンドライン引数 の順に上書きする
+      設定ファイル: `--config <path>` または AHC033_CONFIG で指定し、`key = value` を 1 行ずつ書く (# 以降はコメント)
+      環境変数: AHC033_MAX_WIDTH のように key を大文字にして AHC033_ を付ける
+      コマンドライン引数: `--max-width 5000` または `--max-width=5000` (key の _ は - にする)
+*/
+#[derive(Clone, Debug)]
+pub struct SolverConfig {
+    pub max_width: usize,         // ビーム幅の上限
+    pub min_width: usize,         // 時間が足りなくてもこれ以上は幅を絞らない
+    pub turn: usize,              // ビームサーチの最大層数 (0 なら N に合わせて決める)
+    pub using_crane: usize,       // 動かすクレーンの台数 (0 なら全台)
+    pub crane_perm: Vec<usize>,   // 1 台ずつ動かす場合のクレーンの順番 (空なら 0..using_crane)
+    pub time_limit: f64,          // ビームサーチと局所探索に使う時間 (秒)
+    pub joint_move: bool,         // true の場合は 1 ターンで全クレーンを同時に動かす
+    pub planner: bool,            // true の場合はビームサーチを使わずにプランナーで解く
+    pub evaluator: EvaluatorKind, // ビームサーチの評価関数
+    pub penalty: bool,            // true の場合は順番違い・誤った搬出口への搬出も許す
+    pub search: SearchKind,       // 探索方法 (ビームサーチか chokudai サーチか)
+    pub chokudai_width: usize,    // chokudai サーチで 1 周ごとに各深さから展開するノード数
+    pub post_time: f64, // time_limit のうち、出力を局所探索で短くするのに使う時間 (秒、0 ならしない)
+    pub complete_time: f64, // time_limit のうち、終わらなかったビームサーチの続きをプランナーで計画する時間 (秒、0 なら N に合わせて決める)
+}
+impl Default for SolverConfig {
+    fn default() -> Self {
+        Self {
+            max_width: 10000,
+            min_width: 1000,
+            turn: 0,
+            using_crane: 0,
+            crane_perm: vec![],
+            time_limit: 2.8,
+            joint_move: false,
+            planner: false,
+            evaluator: EvaluatorKind::Potential,
+            penalty: false,
+            search: SearchKind::Beam,
+            chokudai_width: 1,
+            post_time: 0.1,
+            complete_time: 0.0,
+        }
+    }
+}
+impl SolverConfig {
+    pub const ENV_PREFIX: &'static str = "AHC033_";
+
+    /* 設定ファイル・環境変数・コマンドライン引数から設定を読み込む関数 */
+    pub fn load() -> Result<Self, String> {
+        let args: Vec<String> = std::env::args().skip(1).collect();
+        let flags = Self::parse_args(&args)?;
+
+        let mut config = Self::default();
+
+        // 設定ファイル
+        let path = flags
+            .iter()
+            .find(|(key, _)| key == "config")
+            .map(|(_, value)| value.clone())
+            .or_else(|| std::env::var(format!("{}CONFIG", Self::ENV_PREFIX)).ok());
+        if let Some(path) = path {
+            let text = std::fs::read_to_string(&path).map_err(|e| format!("{}: {}", path, e))?;
+            for (i, line) in text.lines().enumerate() {
+                let line = line.split('#').next().unwrap().trim();
+                if line.is_empty() {
+                    continue;
+                }
+                let (key, value) = line
+                    .split_once('=')
+                    .ok_or_else(|| format!("{}:{}: expected `key = value`", path, i + 1))?;
+                config.set(key.trim(), value.trim())?;
+            }
+        }
+
+        // 環境変数
+        for key in Self::KEYS {
+            if let Ok(value) = std::env::var(format!("{}{}", Self::ENV_PREFIX, key.to_uppercase()))
+            {
+                config.set(key, &value)?;
+            }
+        }
+
+        // コマンドライン引数
+        for (key, value) in flags.iter().filter(|(key, _)| key != "config") {
+            config.set(key, value)?;
+        }
+
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub const KEYS: [&'static str; 14] = [
+        "max_width",
+        "min_width",
+        "turn",
+        "using_crane",
+        "crane_perm",
+        "time_limit",
+        "joint_move",
+        "planner",
+        "evaluator",
+        "penalty",
+        "search",
+        "chokudai_width",
+        "post_time",
+        "complete_time",
+    ];
+
+    /* `--key value` / `--key=value` の組を (key, value) の列にする関数 */
+    pub fn parse_args(args: &[String]) -> Result<Vec<(String, String)>, String> {
+        let mut flags = vec![];
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            let flag = arg
+                .strip_prefix("--")
+                .ok_or_else(|| format!("unexpected argument: {}", arg))?;
+            let (key, value) = match flag.split_once('=') {
+                Some((key, value)) => (key.to_string(), value.to_string()),
+                None => {
+                    let value = iter
+                        .next()
+                        .ok_or_else(|| format!("missing value for --{}", flag))?;
+                    (flag.to_string(), value.clone())
+                }
+            };
+            flags.push((key.replace('-', "_"), value));
+        }
+        Ok(flags)
+    }
+
+    /* key に value を設定する関数 */
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        fn parse<T: std::str::FromStr>(key: &str, value: &str) -> Result<T, String> {
+            value
+                .parse()
+                .map_err(|_| format!("invalid value for {}: {}", key, value))
+        }
+
+        match key {
+            "max_width" => self.max_width = parse(key, value)?,
+            "min_width" => self.min_width = parse(key, value)?,
+            "turn" => self.turn = parse(key, value)?,
+            "using_crane" => self.using_crane = parse(key, value)?,
+            "crane_perm" => {
+                self.crane_perm = value
+                    .split(',')
+                    .map(|c| parse(key, c.trim()))
+                    .collect::<Result<_, _>>()?;
+            }
+            "time_limit" => self.time_limit = parse(key, value)?,
+            "joint_move" => self.joint_move = parse(key, value)?,
+            "planner" => self.planner = parse(key, value)?,
+            "evaluator" => self.evaluator = parse(key, value)?,
+            "penalty" => self.penalty = parse(key, value)?,
+            "search" => self.search = parse(key, value)?,
+            "chokudai_width" => self.chokudai_width = parse(key, value)?,
+            "post_time" => self.post_time = parse(key, value)?,
+            "complete_time" => self.complete_time = parse(key, value)?,
+            _ => return Err(format!("unknown key: {}", key)),
+        }
+        Ok(())
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.max_width == 0 {
+            return Err("max_width must be positive".to_string());
+        }
+        if self.min_width > self.max_width {
+            return Err(format!(
+                "min_width ({}) is larger than max_width ({})",
+                self.min_width, self.max_width
+            ));
+        }
+        if self.penalty && self.evaluator != EvaluatorKind::ScoreAware {
+            // ペナルティの重みを持つのは score だけ
+            return Err("penalty requires evaluator = score".to_string());
+        }
+        if self.chokudai_width == 0 {
+            return Err("chokudai_width must be positive".to_string());
+        }
+        if self.post_time < 0.0 || self.complete_time < 0.0 {
+            return Err("post_time and complete_time must not be negative".to_string());
+        }
+        Ok(())
+    }
+
+    /* ビームサーチに使える時間 (time_limit から局所探索と続きの計画の分を除く) */
+    pub fn search_time(&self) -> f64 {
+        self.time_limit - self.post_time - self.complete_time
+    }
+
+    /*
+        入力の N に合わせて using_crane と crane_perm を確定させる関数
+        using_crane が 0 なら N 台すべて、crane_perm が空なら 0..using_crane の順に動かす
+    */
+    pub fn resolve(&mut self, n: usize) -> Result<(), String> {
+        // 評価値の重み 10^(N + 2) が i64 に収まる範囲
+        if !(MIN_N..=MAX_N).contains(&n) {
+            return Err(format!("N must be in {}..={}: {}", MIN_N, MAX_N, n));
+        }
+        if self.turn == 0 {
+            // 層数はおおよそ (ターン数 ∝ N^2) × (クレーン数 N) に比例する
+            self.turn = 1000 * n * n * n / (BASE_N * BASE_N * BASE_N);
+        }
+        if self.complete_time == 0.0 {
+            // プランナーの計画にかかる時間もおおよそ N^3 に比例する
+            self.complete_time = 0.1 * (n * n * n) as f64 / (BASE_N * BASE_N * BASE_N) as f64;
+        }
+        if self.post_time + self.complete_time >= self.time_limit {
+            // 局所探索と続きの計画の時間は time_limit の中から取る
+            return Err(format!(
+                "post_time + complete_time ({} + {:.2}) must be less than time_limit ({})",
+                self.post_time, self.complete_time, self.time_limit
+            ));
+        }
+        if self.using_crane == 0 {
+            self.using_crane = n;
+        }
+        if self.crane_perm.is_empty() {
+            self.crane_perm = (0..self.using_crane.min(n)).collect();
+        }
+        if !(1..=n).contains(&self.using_crane) {
+            return Err(format!(
+                "using_crane must be in 1..={}: {}",
+                n, self.using_crane
+            ));
+        }
+        let mut used = vec![false; n];
+        for &c in self.crane_perm.iter() {
+            if c >= n || used[c] {
+                return Err(format!("invalid crane_perm: {:?}", self.crane_perm));
+            }
+            used[c] = true;
+        }
+        if self.crane_perm.len() != self.using_crane {
+            return Err(format!(
+                "crane_perm has {} cranes but using_crane is {}",
+                self.crane_perm.len(),
+                self.using_crane
+            ));
+        }
+        Ok(())
+    }
+}
+
+pub const MIN_N: usize = 2;
+pub const MAX_N: usize = 10;
+
+pub struct Input {
+    pub n: usize,
+    pub a: Vec<Vec<i64>>,
+}
+impl Input {
+    pub fn read_input() -> Self {
+        input! {
+            n: usize,
+            a: [[i64; n]; n],
+        }
+        Self { n, a }
+    }
+}
+
+#[derive(Clone, PartialEq)]
+pub struct Crane {
+    pub h: usize,
+    pub w: usize,
+    pub x: usize,
+    pub y: usize,
+    pub idx: usize,
+    pub pre_op: usize,
+    pub big: bool,
+    pub suspended: bool,
+    pub exploded: bool,
+    pub penalty_ok: bool, // 順番違い・誤った搬出口にも降ろせるか (SolverConfig::penalty)
+}
+impl Crane {
+    pub fn new(input: &Input, _idx: usize, _x: usize, _y: usize, _big: bool) -> Self {
+        Self {
+            h: input.n,
+            w: input.n,
+            x: _x,
+            y: _y,
+            idx: _idx,
+            suspended: false,
+            big: _big,
+            exploded: false,
+            pre_op: !0,
+            penalty_ok: false,
+        }
+    }
+
+    pub fn shift(
+        &mut self,
+        dir: usize,
+        grid_crane: &mut [Vec<isize>],
+        grid_cont: &mut [Vec<Vec<i64>>],
+        cont_suspended: &mut [Vec<Vec<bool>>],
+    ) -> char {
+        let nx = (self.x as isize + DX[dir]) as usize;
+        let ny = (self.y as isize + DY[dir]) as usize;
+        grid_crane[nx][ny] = self.idx as isize;
+        grid_crane[self.x][self.y] = -1;
+
+        if self.suspended {
+            grid_cont[nx][ny][self.big as usize] = grid_cont[self.x][self.y][self.big as usize];
+            grid_cont[self.x][self.y][self.big as usize] = -1;
+            cont_suspended[nx][ny][self.big as usize] = true;
+            cont_suspended[self.x][self.y][self.big as usize] = false;
+        }
+
+        self.x = nx;
+        self.y = ny;
+        self.pre_op = dir;
+        DIR[dir]
+    }
+
+    pub fn suspend(
+        &mut self,
+        grid_cont: &mut [Vec<Vec<i64>>],
+        cont_suspended: &mut [Vec<Vec<bool>>],
+    ) -> char {
+        self.suspended = true;
+        cont_suspended[self.x][self.y][self.big as usize] = true;
+        if self.big {
+            // assert!(grid_cont[self.x][self.y][0] != -1);
+            grid_cont[self.x][self.y].swap(0, 1);
+        }
+        self.pre_op = Operation::Suspend as usize;
+        'P'
+    }
+
+    pub fn lower(
+        &mut self,
+        grid_cont: &mut [Vec<Vec<i64>>],
+        cont_suspended: &mut [Vec<Vec<bool>>],
+    ) -> char {
+        self.suspended = false;
+        cont_suspended[self.x][self.y][self.big as usize] = false;
+        if self.big {
+            // assert!(grid_cont[self.x][self.y][1] != -1);
+            grid_cont[self.x][self.y].swap(0, 1);
+        }
+        self.pre_op = Operation::Lower as usize;
+        'Q'
+    }
+
+    pub fn stop(&mut self) -> char {
+        self.pre_op = Operation::Stop as usize;
+        '.'
+    }
+
+    pub fn explode(&mut self, grid_crane: &mut [Vec<isize>]) -> char {
+        grid_crane[self.x][self.y] = -1;
+        self.exploded = true;
+        self.pre_op = Operation::Bomb as usize;
+        'B'
+    }
+
+    pub fn action(
+        &mut self,
+        action: usize,
+        grid_crane: &mut [Vec<isize>],
+        grid_cont: &mut [Vec<Vec<i64>>],
+        cont_suspended: &mut [Vec<Vec<bool>>],
+    ) -> char {
+        match action {
+            0..=3 => self.shift(action, grid_crane, grid_cont, cont_suspended),
+            4 => self.suspend(grid_cont, cont_suspended),
+            5 => self.lower(grid_cont, cont_suspended),
+            6 => self.stop(),
+            7 => self.explode(grid_crane),
+            _ => panic!("invalid action"),
+        }
+    }
+
+    pub fn shift_ok(
+        &self,
+        dir: usize,
+        grid_crane: &[Vec<isize>],
+        grid_cont: &[Vec<Vec<i64>>],
+    ) -> bool {
+        let nx = self.x as isize + DX[dir];
+        let ny = self.y as isize + DY[dir];
+        if out_field(nx, ny, self.h as isize, self.w as isize) {
+            // フィールド外に出る場合は NG
+            return false;
+        }
+        let nx = nx as usize;
+        let ny = ny as usize;
+
+        if grid_crane[nx][ny] != -1 {
+            // 移動先にクレーンがいる場合は NG
+            // 1 台ずつ動かす場合は同じターンに先に動いたクレーンの移動後の位置を見るので、
+            // 2 台がすれ違うにはお互いが相手より先に動く必要があり、ジャッジが禁止するすれ違いは起こらない
+            return false;
+        }
+
+        if !self.big && self.suspended && grid_cont[nx][ny][0] != -1 {
+            // 小クレーンで吊り上げていて、移動先にコンテナがある場合は NG
+            return false;
+        }
+        true
+    }
+
+    pub fn suspend_ok(&self, grid_cont: &[Vec<Vec<i64>>], out_cont_idx: &[usize]) -> bool {
+        if self.suspended {
+            // 吊り上げている場合は NG
+            return false;
+        }
+
+        if grid_cont[self.x][self.y][0] == -1 {
+            // 吊り上げるコンテナがない場合は NG
+            return false;
+        }
+
+        if self.idx == 0 {
+            // crane 1 の場合は、今すぐ搬出可能なコンテナでない場合は NG
+            let mut flag = false;
+            for idx in out_cont_idx.iter().take(self.h) {
+                flag |= *idx == grid_cont[self.x][self.y][0] as usize;
+            }
+            if !flag {
+                return false;
+            }
+        }
+        true
+    }
+
+    pub fn lower_ok(&self, grid_cont: &[Vec<Vec<i64>>], out_cont_idx: &[usize]) -> bool {
+        if !self.suspended {
+            // 吊り上げていない場合は NG
+            return false;
+        }
+
+        if self.big && grid_cont[self.x][self.y][0] != -1 {
+            // 大クレーンで吊り下げ中で、降ろす場所にコンテナがある場合は NG
+            return false;
+        }
+
+        if self.y == 1 && self.y != self.w - 1 {
+            // 交通の便をよくするために、1 列目には降ろせない (N = 2 では 1 列目が搬出口)
+            return false;
+        }
+
+        let cond_idx: usize = grid_cont[self.x][self.y][self.big as usize] as usize;
+        let x = cond_idx / self.h;
+        if !(self.y != self.w - 1 || self.penalty_ok || self.x == x && out_cont_idx[x] == cond_idx)
+        {
+            // 降ろす場所が不適の場合は NG
+            return false;
+        }
+        true
+    }
+
+    pub fn bomb_ok(&self) -> bool {
+        // 大クレーンは残す。吊り上げ中は爆破できない
+        !self.big && !self.suspended
+    }
+
+    pub fn stop_ok(&self) -> bool {
+        // 待機はいつでも可能 (1 ターン分のコストは評価値側で考慮)
+        true
+    }
+
+    pub fn action_ok(
+        &self,
+        action: usize,
+        grid_crane: &[Vec<isize>],
+        grid_cont: &[Vec<Vec<i64>>],
+        out_cont_idx: &[usize],
+    ) -> bool {
+        if self.exploded {
+            // 爆破済みのクレーンは何もできない
+            return false;
+        }
+        match action {
+            0..=3 => self.shift_ok(action, grid_crane, grid_cont),
+            4 => self.suspend_ok(grid_cont, out_cont_idx),
+            5 => self.lower_ok(grid_cont, out_cont_idx),
+            6 => self.stop_ok(),
+            7 => self.bomb_ok(),
+            _ => panic!("invalid action"),
+        }
+    }
+}
+
+/* Zobrist Hash の乱数表 */
+#[derive(Clone, PartialEq)]
+pub struct Zobrist {
+    pub h: usize,
+    pub w: usize,
+    pub crane: Vec<u64>, // クレーン c が (x, y) にいて吊り上げ中か否か
+    pub cont: Vec<u64>,  // コンテナ k が (x, y) の上空(1) or 接地(0) にある
+    pub out: Vec<u64>,   // コンテナ k が搬出済み
+}
+impl Zobrist {
+    pub fn new(h: usize, w: usize) -> Self {
+        use rand::{Rng, SeedableRng};
+        let mut rng = rand::rngs::StdRng::seed_from_u64(33);
+        let mut table = |len: usize| (0..len).map(|_| rng.gen::<u64>()).collect::<Vec<u64>>();
+        Self {
+            h,
+            w,
+            crane: table(h * h * w * 2),
+            cont: table(h * w * h * w * 2),
+            out: table(h * w),
+        }
+    }
+
+    pub fn crane(&self, c: usize, x: usize, y: usize, suspended: bool) -> u64 {
+        self.crane[((c * self.h + x) * self.w + y) * 2 + suspended as usize]
+    }
+
+    pub fn cont(&self, k: i64, x: usize, y: usize, layer: usize) -> u64 {
+        self.cont[((k as usize * self.h + x) * self.w + y) * 2 + layer]
+    }
+
+    pub fn out(&self, k: i64) -> u64 {
+        self.out[k as usize]
+    }
+}
+
+/* apply 1 回分で書き戻す Terminal のスカラー値と、undo log に積んだ要素数 */
+#[derive(Clone, PartialEq)]
+pub struct UndoFrame {
+    pub score: i64,
+    pub hash: u64,
+    pub turn: usize,
+    pub out_cnt: usize,
+    pub appeared_cnt: usize,
+    pub dispatched: u128,
+    pub inversions: usize,
+    pub wrong_gate: usize,
+    pub cells: usize,  // UndoLog::cells に積んだマスの数
+    pub cranes: usize, // UndoLog::cranes に積んだクレーンの数
+}
+
+// apply 前のマス (x, y, grid_crane, grid_cont, cont_suspended)
+pub type UndoCell = (usize, usize, isize, [i64; 2], [bool; 2]);
+
+/* revert 用に apply 前の状態を積んでおくスタック */
+#[derive(Clone, Default, PartialEq)]
+pub struct UndoLog {
+    pub frames: Vec<UndoFrame>,
+    pub cells: Vec<UndoCell>,
+    pub cranes: Vec<(usize, Crane)>, // apply 前の操作したクレーン
+    pub cont_idx: Vec<usize>,        // apply 前の (out_cont_idx, incoming_cont_idx)
+}
+
+#[derive(Clone, PartialEq)]
+pub struct Terminal<E = Potential> {
+    pub h: usize,
+    pub w: usize,
+    pub score: i64,
+    pub turn: usize,
+    pub out_cnt: usize,                      // 搬出済みのコンテナ数
+    pub appeared_cnt: usize,                 // 場に出現したコンテナ数
+    pub dispatched: u128,                    // 搬出済みのコンテナ (N^2 <= 100 ビット)
+    pub inversions: usize,                   // 順番違いの搬出で確定した転倒数
+    pub wrong_gate: usize,                   // 誤った搬出口から搬出したコンテナ数
+    pub hash: u64,                           // Zobrist Hash
+    pub conts: Vec<Vec<i64>>,                // 行 i から j 番目に来るコンテナの index
+    pub out_cont_idx: Vec<usize>,            // 各搬出口から今搬出すべきコンテナの index
+    pub incoming_cont_idx: Vec<usize>,       // 各搬入口から今搬入すべきコンテナの index
+    pub grid_cont: Vec<Vec<Vec<i64>>>,       // (i, j) で上空(1) or 接地(0) が k のコンテナの index
+    pub grid_crane: Vec<Vec<isize>>,         // (i, j) にいるクレーンの index
+    pub cranes: Vec<Crane>,                  // 各クレーンの情報
+    pub cont_suspended: Vec<Vec<Vec<bool>>>, // (i, j) にあるコンテナが吊り上げられているか
+
+    pub evaluator: E,                  // score の計算方法
+    pub zobrist: std::rc::Rc<Zobrist>, // hash の乱数表
+    pub undo: UndoLog,                 // revert で書き戻す apply 前の状態
+
+    pub crane_perm: Vec<usize>, // 1 台ずつ動かす場合のクレーンの順番
+    pub joint_move: bool,       // 全クレーンを同時に動かすか
+}
+impl<E: Evaluator> Terminal<E> {
+    pub fn new(input: &Input, config: &SolverConfig, evaluator: E) -> Self {
+        // 搬出するコンテナの index 初期化
+        let mut _out_cont_idx: Vec<usize> = vec![0; input.n];
+        for (i, cont_idx) in _out_cont_idx.iter_mut().enumerate() {
+            *cont_idx = i * input.n;
+        }
+
+        // クレーンをターミナル上で初期化
+        let mut _cranes: Vec<Crane> = vec![];
+        let mut _grid_crane: Vec<Vec<isize>> = vec![vec![-1; input.n]; input.n];
+        for (i, crane) in _grid_crane.iter_mut().enumerate().take(input.n) {
+            let big = i == 0;
+            _cranes.push(Crane {
+                penalty_ok: config.penalty,
+                ..Crane::new(input, i, i, 0, big)
+            });
+            crane[0] = i as isize;
+        }
+
+        Self {
+            h: input.n,
+            w: input.n,
+            score: 1,
+            turn: 0,
+            out_cnt: 0,
+            appeared_cnt: 0,
+            dispatched: 0,
+            inversions: 0,
+            wrong_gate: 0,
+            hash: 0,
+            conts: input.a.to_vec(),
+            out_cont_idx: _out_cont_idx,
+            incoming_cont_idx: vec![0; input.n],
+            grid_cont: vec![vec![vec![-1; 2]; input.n]; input.n],
+            grid_crane: _grid_crane,
+            cranes: _cranes,
+            cont_suspended: vec![vec![vec![false; 2]; input.n]; input.n],
+            evaluator,
+            zobrist: std::rc::Rc::new(Zobrist::new(input.n, input.n)),
+            undo: UndoLog::default(),
+            crane_perm: config.crane_perm.clone(),
+            joint_move: config.joint_move,
+        }
+    }
+
+    /*
+        搬入口が空いてる時にコンテナを搬入する関数
+        ジャッジと同じく、地面にコンテナがなく、コンテナを吊り上げたクレーンもいない搬入口にだけ搬入する
+        (1 台ずつ動かす場合も、全クレーンが動き終えたターンの終わりにだけ呼ぶこと)
+    */
+    pub fn prepare_cont(&mut self) {
+        for i in 0..self.h {
+            let crane = self.grid_crane[i][0];
+            let holding = crane != -1 && self.cranes[crane as usize].suspended;
+            // 搬入口が空いている場合
+            if self.grid_cont[i][0][0] == -1 && !holding && self.incoming_cont_idx[i] < self.w {
+                // 差分更新でスコア更新
+                self.score += self.evaluator.carry_in_diff(self, i);
+
+                // コンテナを搬入
+                self.save_cell(i, 0);
+                self.grid_cont[i][0][0] = self.conts[i][self.incoming_cont_idx[i]];
+                self.appeared_cnt += 1;
+                self.hash ^= self.zobrist.cont(self.grid_cont[i][0][0], i, 0, 0);
+
+                // 次に搬入すべきコンテナを更新
+                self.incoming_cont_idx[i] += 1;
+            }
+        }
+    }
+
+    /* 搬出口にあるコンテナを搬出する関数 */
+    pub fn carry_out_cont(&mut self) {
+        for i in 0..self.h {
+            // コンテナ搬出
+            if self.grid_cont[i][self.w - 1][0] != -1 && !self.cont_suspended[i][self.w - 1][0] {
+                // 差分更新でスコア更新
+                let cont = self.grid_cont[i][self.w - 1][0];
+                self.score += self.evaluator.carry_out_diff(self, i, cont);
+
+                // ペナルティを数える
+                let (inversions, wrong_gate) = self.dispatch_penalty(i, cont);
+                self.inversions += inversions;
+                self.wrong_gate += wrong_gate;
+
+                // 次に搬出すべきコンテナに更新
+                self.dispatched |= 1 << cont;
+                let g = cont as usize / self.h;
+                while self.out_cont_idx[g] < (g + 1) * self.h
+                    && self.dispatched >> self.out_cont_idx[g] & 1 == 1
+                {
+                    self.out_cont_idx[g] += 1;
+                }
+
+                // 搬出済みなのでコンテナ情報をクリア
+                self.save_cell(i, self.w - 1);
+                self.hash ^= self.zobrist.cont(cont, i, self.w - 1, 0) ^ self.zobrist.out(cont);
+                self.grid_cont[i][self.w - 1][0] = -1;
+                self.appeared_cnt -= 1;
+
+                // 搬出したコンテナ数を更新
+                self.out_cnt += 1;
+
+                if self.out_cnt == self.h * self.w {
+                    // 全てのコンテナを搬出した場合は終了 (残るのはペナルティだけ)
+                    self.score = self.evaluate();
+                    return;
+                }
+            }
+        }
+    }
+
+    /*
+        搬出口 i からコンテナ cont を搬出した時に増える (転倒数, 誤った搬出口の数)
+        転倒数は、同じ搬出口に後から搬出することになる cont より小さいコンテナの数で数える
+    */
+    pub fn dispatch_penalty(&self, i: usize, cont: i64) -> (usize, usize) {
+        let g = cont as usize / self.h;
+        if g != i {
+            return (0, 1);
+        }
+        let smaller = (g * self.h..cont as usize)
+            .filter(|&c| self.dispatched >> c & 1 == 0)
+            .count();
+        (smaller, 0)
+    }
+
+    /* 盤面の Zobrist Hash を一から計算する関数 */
+    pub fn compute_hash(&self) -> u64 {
+        let mut hash = 0;
+        for (c, crane) in self
+            .cranes
+            .iter()
+            .enumerate()
+            .filter(|(_, crane)| !crane.exploded)
+        {
+            hash ^= self.zobrist.crane(c, crane.x, crane.y, crane.suspended);
+        }
+        for x in 0..self.h {
+            for y in 0..self.w {
+                for layer in 0..2 {
+                    if self.grid_cont[x][y][layer] != -1 {
+                        hash ^= self.zobrist.cont(self.grid_cont[x][y][layer], x, y, layer);
+                    }
+                }
+            }
+            // 搬出口 x 行きの搬出済みのコンテナ
+            for cont in (x * self.h)..((x + 1) * self.h) {
+                if self.dispatched >> cont & 1 == 1 {
+                    hash ^= self.zobrist.out(cont as i64);
+                }
+            }
+        }
+        hash
+    }
+
+    /* クレーン c が操作 op をした時の hash の差分 (搬入・搬出は含まない) */
+    pub fn action_hash_diff(&self, c: usize, op: usize) -> u64 {
+        let crane = &self.cranes[c];
+        let (x, y) = (crane.x, crane.y);
+        let z = &self.zobrist;
+        match op {
+            0..=3 => {
+                let (nx, ny) = (
+                    (x as isize + DX[op]) as usize,
+                    (y as isize + DY[op]) as usize,
+                );
+                let mut diff =
+                    z.crane(c, x, y, crane.suspended) ^ z.crane(c, nx, ny, crane.suspended);
+                if crane.suspended {
+                    let layer = crane.big as usize;
+                    let cont = self.grid_cont[x][y][layer];
+                    diff ^= z.cont(cont, x, y, layer) ^ z.cont(cont, nx, ny, layer);
+                }
+                diff
+            }
+            4 | 5 => {
+                let mut diff = z.crane(c, x, y, false) ^ z.crane(c, x, y, true);
+                if crane.big {
+                    // 大クレーンは接地(0) と上空(1) を入れ替える
+                    let cont = self.grid_cont[x][y][(op == 5) as usize];
+                    diff ^= z.cont(cont, x, y, 0) ^ z.cont(cont, x, y, 1);
+                }
+                diff
+            }
+            // 爆破したクレーンは盤面から消える
+            7 => z.crane(c, x, y, false),
+            _ => 0,
+        }
+    }
+
+    /* 1 台ずつ動かす場合に、このターンに動かすクレーンの index */
+    pub fn moving_crane(&self) -> usize {
+        self.crane_perm[self.turn % self.crane_perm.len()]
+    }
+
+    /* 次のノードに遷移する関数 */
+    pub fn apply(&mut self, node: &Node) {
+        if self.joint_move {
+            self.apply_joint(&decode_joint(node.op, self.cranes.len()));
+            return;
+        }
+        let action = node.op;
+        let c = self.moving_crane();
+        self.save_undo(&[(c, action)]);
+
+        // 差分更新でスコア・hash 更新
+        if action < 4 {
+            self.score += self.evaluator.shift_diff(self, c, action);
+        }
+        self.score += self.idle_costs(c)[action];
+        if action == Operation::Bomb as usize {
+            self.score += self.evaluator.bomb_cost(self);
+        }
+        self.hash ^= self.action_hash_diff(c, action);
+
+        self.cranes[c].action(
+            action,
+            &mut self.grid_crane,
+            &mut self.grid_cont,
+            &mut self.cont_suspended,
+        );
+        self.carry_out_cont();
+        // 搬入はジャッジと同じくターンの終わりに行う (途中で搬入すると、後から動くクレーンが搬入口に入れるかが変わる)
+        if c == *self.crane_perm.last().unwrap() {
+            self.prepare_cont();
+        }
+        self.turn += 1;
+    }
+
+    /*
+        1 台ずつ動かす場合に、クレーン c の各操作で無駄になる 1 ターン分のコスト
+        待機と、何も吊り上げていないクレーンが吊り上げに向かうべきコンテナ (大クレーンは今すぐ搬出できるもの) に近づかない移動は搬出を進めないので、1 ターン分を課す
+        (隣のクレーンに道を空けるための移動は除く)
+    */
+    pub fn idle_costs(&self, c: usize) -> [i64; OP_NUM] {
+        let mut costs = [0; OP_NUM];
+        let crane = &self.cranes[c];
+        if crane.exploded {
+            return costs;
+        }
+        let turn_cost = self.evaluator.turn_cost(self);
+        costs[Operation::Stop as usize] = turn_cost;
+        if crane.suspended
+            || self.cranes.iter().any(|other| {
+                !other.exploded && other.x.abs_diff(crane.x) + other.y.abs_diff(crane.y) == 1
+            })
+        {
+            return costs;
+        }
+
+        // dist[dir] は dir 方向に動いた後の、dist[DIR_NUM] は今の位置からの最寄りのコンテナまでの距離
+        let mut dist = [!0; DIR_NUM + 1];
+        for x in 0..self.h {
+            for y in 0..self.w {
+                if (x, y) == (crane.x, crane.y) || !self.cont_on_ground(x, y) {
+                    continue;
+                }
+                if crane.big
+                    && !self
+                        .out_cont_idx
+                        .contains(&(self.grid_cont[x][y][0] as usize))
+                {
+                    continue;
+                }
+                for (dir, d) in dist.iter_mut().enumerate() {
+                    let (dx, dy) = if dir < DIR_NUM {
+                        (DX[dir], DY[dir])
+                    } else {
+                        (0, 0)
+                    };
+                    let (cx, cy) = (crane.x as isize + dx, crane.y as isize + dy);
+                    *d = (*d)
+                        .min((x as isize - cx).unsigned_abs() + (y as isize - cy).unsigned_abs());
+                }
+            }
+        }
+        for dir in 0..DIR_NUM {
+            if dist[dir] >= dist[DIR_NUM] {
+                costs[dir] = turn_cost;
+            }
+        }
+        costs
+    }
+
+    /* 前のノードに遷移する関数 (直前の apply で変更された状態を undo log から書き戻す) */
+    pub fn revert(&mut self) {
+        let frame = self.undo.frames.pop().expect("revert without apply");
+        self.score = frame.score;
+        self.hash = frame.hash;
+        self.turn = frame.turn;
+        self.out_cnt = frame.out_cnt;
+        self.appeared_cnt = frame.appeared_cnt;
+        self.dispatched = frame.dispatched;
+        self.inversions = frame.inversions;
+        self.wrong_gate = frame.wrong_gate;
+
+        for _ in 0..frame.cells {
+            let (x, y, crane, cont, suspended) = self.undo.cells.pop().unwrap();
+            self.grid_crane[x][y] = crane;
+            self.grid_cont[x][y].copy_from_slice(&cont);
+            self.cont_suspended[x][y].copy_from_slice(&suspended);
+        }
+        for _ in 0..frame.cranes {
+            let (c, crane) = self.undo.cranes.pop().unwrap();
+            self.cranes[c] = crane;
+        }
+        for i in (0..self.h).rev() {
+            self.incoming_cont_idx[i] = self.undo.cont_idx.pop().unwrap();
+            self.out_cont_idx[i] = self.undo.cont_idx.pop().unwrap();
+        }
+    }
+
+    /* (クレーン, 操作) の列を適用する前の状態を undo log に積む関数 */
+    pub fn save_undo(&mut self, ops: &[(usize, usize)]) {
+        self.undo.frames.push(UndoFrame {
+            score: self.score,
+            hash: self.hash,
+            turn: self.turn,
+            out_cnt: self.out_cnt,
+            appeared_cnt: self.appeared_cnt,
+            dispatched: self.dispatched,
+            inversions: self.inversions,
+            wrong_gate: self.wrong_gate,
+            cells: 0,
+            cranes: 0,
+        });
+        for i in 0..self.h {
+            self.undo.cont_idx.push(self.out_cont_idx[i]);
+            self.undo.cont_idx.push(self.incoming_cont_idx[i]);
+        }
+
+        // 操作するクレーンと、その移動元・移動先
+        for &(c, op) in ops.iter() {
+            let crane = self.cranes[c].clone();
+            self.undo.cranes.push((c, crane.clone()));
+            self.undo.frames.last_mut().unwrap().cranes += 1;
+            if crane.exploded {
+                continue;
+            }
+            self.save_cell(crane.x, crane.y);
+            if op < 4 {
+                self.save_cell(
+                    (crane.x as isize + DX[op]) as usize,
+                    (crane.y as isize + DY[op]) as usize,
+                );
+            }
+        }
+    }
+
+    /* 変更する前のマス (x, y) を undo log に積む関数 (apply の外では何もしない) */
+    pub fn save_cell(&mut self, x: usize, y: usize) {
+        let Some(frame) = self.undo.frames.last_mut() else {
+            return;
+        };
+        frame.cells += 1;
+        self.undo.cells.push((
+            x,
+            y,
+            self.grid_crane[x][y],
+            [self.grid_cont[x][y][0], self.grid_cont[x][y][1]],
+            [self.cont_suspended[x][y][0], self.cont_suspended[x][y][1]],
+        ));
+    }
+
+    /* apply してから revert すると元の状態に戻ることを確認する (デバッグ用) */
+    #[cfg(debug_assertions)]
+    fn check_revert(&mut self, node: &Node) {
+        // undo log 自体は比較から外して、複製を軽くする
+        let undo = std::mem::take(&mut self.undo);
+        let before = self.clone();
+        self.undo = undo;
+        let depth = self.undo.frames.len();
+
+        self.apply(node);
+        self.revert();
+
+        let undo = std::mem::take(&mut self.undo);
+        assert!(
+            *self == before,
+            "apply -> revert で元に戻らない (op: {})",
+            node.op
+        );
+        self.undo = undo;
+        assert_eq!(self.undo.frames.len(), depth);
+    }
+
+    /* 全クレーンの移動を同時に行う関数 (tools::State::apply と同じく一斉に動かす) */
+    pub fn shift_joint(&mut self, dirs: &[usize]) {
+        // 移動するクレーンを盤面から外し、吊り上げ中のコンテナを退避
+        let mut carried = vec![-1; self.cranes.len()];
+        for (c, &dir) in dirs.iter().enumerate() {
+            if dir >= 4 {
+                continue;
+            }
+            let crane = &self.cranes[c];
+            self.grid_crane[crane.x][crane.y] = -1;
+            if crane.suspended {
+                carried[c] = self.grid_cont[crane.x][crane.y][crane.big as usize];
+                self.grid_cont[crane.x][crane.y][crane.big as usize] = -1;
+                self.cont_suspended[crane.x][crane.y][crane.big as usize] = false;
+            }
+        }
+
+        // 移動先に配置し直す
+        for (c, &dir) in dirs.iter().enumerate() {
+            if dir >= 4 {
+                continue;
+            }
+            let crane = &mut self.cranes[c];
+            crane.x = (crane.x as isize + DX[dir]) as usize;
+            crane.y = (crane.y as isize + DY[dir]) as usize;
+            crane.pre_op = dir;
+            self.grid_crane[crane.x][crane.y] = c as isize;
+            if crane.suspended {
+                self.grid_cont[crane.x][crane.y][crane.big as usize] = carried[c];
+                self.cont_suspended[crane.x][crane.y][crane.big as usize] = true;
+            }
+        }
+    }
+
+    /* 1 ターン分の全クレーンの操作を適用する関数 */
+    pub fn apply_joint(&mut self, ops: &[usize]) {
+        let moves: Vec<(usize, usize)> = ops.iter().copied().enumerate().collect();
+        self.save_undo(&moves);
+
+        // 差分更新でスコア・hash 更新
+        for (c, &op) in ops.iter().enumerate() {
+            if op < 4 {
+                self.score += self.evaluator.shift_diff(self, c, op);
+            }
+            if op == Operation::Bomb as usize {
+                self.score += self.evaluator.bomb_cost(self);
+            }
+        }
+        self.hash ^= self.joint_hash_diff(ops);
+
+        // 吊り上げ・吊り下げはその場で完結するので先に処理
+        for (c, &op) in ops.iter().enumerate() {
+            if op >= 4 {
+                self.cranes[c].action(
+                    op,
+                    &mut self.grid_crane,
+                    &mut self.grid_cont,
+                    &mut self.cont_suspended,
+                );
+            }
+        }
+        self.shift_joint(ops);
+
+        self.carry_out_cont();
+        self.prepare_cont();
+        self.turn += 1;
+    }
+
+    /* 全クレーンが同時に操作した時の hash の差分 (各クレーンは自分のマスしか変えないので独立に足せる) */
+    pub fn joint_hash_diff(&self, ops: &[usize]) -> u64 {
+        ops.iter()
+            .enumerate()
+            .fold(0, |diff, (c, &op)| diff ^ self.action_hash_diff(c, op))
+    }
+
+    /* (x, y) の地面にコンテナが置かれているか (吊り上げ中のものは除く) */
+    pub fn cont_on_ground(&self, x: usize, y: usize) -> bool {
+        self.grid_cont[x][y][0] != -1 && !self.cont_suspended[x][y][0]
+    }
+
+    /* 搬出口 i から地面にコンテナのないマスだけを通った距離 (荷物を持った小クレーンの搬出口までの距離、行けなければ !0) */
+    pub fn gate_dist(&self, i: usize) -> Vec<Vec<usize>> {
+        let mut dist = vec![vec![!0; self.w]; self.h];
+        let mut queue = std::collections::VecDeque::from([(i, self.w - 1)]);
+        dist[i][self.w - 1] = 0;
+        while let Some((x, y)) = queue.pop_front() {
+            for dir in 0..DIR_NUM {
+                let (nx, ny) = (x as isize + DX[dir], y as isize + DY[dir]);
+                if nx < 0 || ny < 0 || nx >= self.h as isize || ny >= self.w as isize {
+                    continue;
+                }
+                let (nx, ny) = (nx as usize, ny as usize);
+                if dist[nx][ny] == !0 && !self.cont_on_ground(nx, ny) {
+                    dist[nx][ny] = dist[x][y] + 1;
+                    queue.push_back((nx, ny));
+                }
+            }
+        }
+        dist
+    }
+
+    /* 全クレーン同時操作時のクレーン c の行動可能判定 (クレーン同士の衝突は別で判定) */
+    pub fn joint_action_ok(&self, c: usize, op: usize) -> bool {
+        let crane = &self.cranes[c];
+        if crane.exploded {
+            // 爆破済みのクレーンは停止のみ
+            return op == Operation::Stop as usize;
+        }
+        match op {
+            0..=3 => {
+                let nx = crane.x as isize + DX[op];
+                let ny = crane.y as isize + DY[op];
+                if out_field(nx, ny, self.h as isize, self.w as isize) {
+                    // フィールド外に出る場合は NG
+                    return false;
+                }
+                // 小クレーンで吊り上げていて、移動先にコンテナがある場合は NG
+                crane.big || !crane.suspended || !self.cont_on_ground(nx as usize, ny as usize)
+            }
+            4 => crane.suspend_ok(&self.grid_cont, &self.out_cont_idx),
+            5 => crane.lower_ok(&self.grid_cont, &self.out_cont_idx),
+            6 => true,
+            7 => crane.bomb_ok(),
+            _ => panic!("invalid action"),
+        }
+    }
+
+    /*
+        1 ターン分の全クレーンの操作 ops がジャッジの規則で合法か (joint_action_ok と違い、探索を絞るための制限はしない)
+        各クレーンの操作を今の盤面で判定し、移動後に同じマスに入る・すれ違うクレーンがいないかを調べる
+    */
+    pub fn joint_legal(&self, ops: &[usize]) -> bool {
+        let mut to = vec![None; self.cranes.len()];
+        for (c, &op) in ops.iter().enumerate() {
+            let crane = &self.cranes[c];
+            if crane.exploded {
+                // 爆破済みのクレーンは停止のみ
+                if op != Operation::Stop as usize {
+                    return false;
+                }
+                continue;
+            }
+            let (mut x, mut y) = (crane.x, crane.y);
+            let ok = match op {
+                0..=3 => {
+                    let nx = x as isize + DX[op];
+                    let ny = y as isize + DY[op];
+                    if out_field(nx, ny, self.h as isize, self.w as isize) {
+                        return false;
+                    }
+                    (x, y) = (nx as usize, ny as usize);
+                    // 小クレーンは吊り上げたままコンテナのあるマスに入れない
+                    crane.big || !crane.suspended || !self.cont_on_ground(x, y)
+                }
+                4 => !crane.suspended && self.cont_on_ground(x, y),
+                5 => crane.suspended && !self.cont_on_ground(x, y),
+                6 => true,
+                7 => !crane.suspended,
+                _ => panic!("invalid action"),
+            };
+            if !ok {
+                return false;
+            }
+            if op != Operation::Bomb as usize {
+                to[c] = Some((x, y));
+            }
+        }
+        for i in 0..to.len() {
+            for j in 0..i {
+                let (Some(ti), Some(tj)) = (to[i], to[j]) else {
+                    continue;
+                };
+                let (pi, pj) = (
+                    (self.cranes[i].x, self.cranes[i].y),
+                    (self.cranes[j].x, self.cranes[j].y),
+                );
+                if ti == tj || (ti == pj && tj == pi) {
+                    return false;
+                }
+            }
+        }
+        true
+    }
+
+    /* 盤面全体の評価値を一から計算する関数 */
+    pub fn evaluate(&self) -> i64 {
+        self.evaluator.evaluate(self)
+    }
+}
+
+/*
+    ビームサーチの評価関数 (小さいほど良く、全て搬出した盤面では順番違い・誤った搬出口のペナルティだけが残るようにする)
+    evaluate で盤面全体から計算し、Terminal の apply / apply_joint と BeamSearch の候補列挙では *_diff の差分だけを足す
+*/
+pub trait Evaluator: Clone + PartialEq + Sized {
+    /* 盤面全体の評価値 */
+    fn evaluate(&self, state: &Terminal<Self>) -> i64;
+    /* クレーン c が吊り上げ中のコンテナを dir 方向に動かした時の差分 (吊り上げていなければ 0) */
+    fn shift_diff(&self, state: &Terminal<Self>, c: usize, dir: usize) -> i64;
+    /* 搬入口 i に次のコンテナを搬入した時の差分 (全て搬入済みなら 0) */
+    fn carry_in_diff(&self, state: &Terminal<Self>, i: usize) -> i64;
+    /* 搬出口 i からコンテナ cont を搬出した時の差分 (ペナルティは Terminal::dispatch_penalty で数える) */
+    fn carry_out_diff(&self, state: &Terminal<Self>, i: usize, cont: i64) -> i64;
+    /* コンテナ cont の重み (同時操作で搬出できないコンテナを持ち続けるペナルティに使う) */
+    fn cont_weight(&self, state: &Terminal<Self>, cont: i64) -> i64;
+    /* 1 ターン分の評価値 (1 台ずつ動かす場合に、搬出を進めない待機・移動ごとに加えるコスト、Terminal::idle_costs) */
+    fn turn_cost(&self, state: &Terminal<Self>) -> i64;
+    /*
+        クレーンを爆破した時の差分
+        爆破したクレーンはこの後の待機・無駄な移動のコストを払わないので、残りのコンテナ 1 個につき 1 ターン分をまとめて課す
+    */
+    fn bomb_cost(&self, state: &Terminal<Self>) -> i64 {
+        self.turn_cost(state) * (state.h * state.w - state.out_cnt) as i64
+    }
+}
+
+/* 評価関数の種類 (SolverConfig::evaluator) */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EvaluatorKind {
+    Potential,
+    ScoreAware,
+}
+impl std::str::FromStr for EvaluatorKind {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "potential" => Ok(Self::Potential),
+            "score" => Ok(Self::ScoreAware),
+            _ => Err(()),
+        }
+    }
+}
+
+/* 探索方法の種類 (SolverConfig::search) */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SearchKind {
+    Beam,
+    Chokudai,
+}
+impl std::str::FromStr for SearchKind {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "beam" => Ok(Self::Beam),
+            "chokudai" => Ok(Self::Chokudai),
+            _ => Err(()),
+        }
+    }
+}
+
+/*
+    ========== 評価関数 ==========
+    目的地点とコンテナの二乗距離を d として、∑_{i,j} d(i,j) * 10^{何番目に搬出すべきか} の最小化を目指す
+    (全て搬出していなければ +1)
+*/
+#[derive(Clone, PartialEq)]
+pub struct Potential {
+    pub cache_prepare_score: Vec<Vec<i64>>, // 搬入口 i から j 番目のコンテナを搬入した時の差分
+}
+impl Potential {
+    pub fn new(input: &Input) -> Self {
+        let mut _cache_prepare_score: Vec<Vec<i64>> = vec![vec![0; input.n + 1]; input.n + 1];
+        #[allow(clippy::needless_range_loop)]
+        for i in 0..input.n {
+            for j in 0..input.n {
+                for k in j..input.n {
+                    let px = i as i64;
+                    let (gx, gy) = (input.a[i][k] / input.n as i64, input.n as i64 - 1);
+                    let perm = input.a[i][k] % input.n as i64;
+
+                    let py1 = -(k as i64 - j as i64 + 1);
+                    let py2 = py1 + 1;
+                    let mut add = 0;
+                    let mut sub = 0;
+
+                    // x 方向の寄与
+                    add += (px - gx) * (px - gx);
+                    sub += (px - gx) * (px - gx);
+                    // y 方向の寄与
+                    add += (py1 - gy) * (py1 - gy);
+                    sub += (py2 - gy) * (py2 - gy);
+                    // 倍率
+                    add *= 10_i64.pow((input.n as i64 - perm) as u32 + 2);
+                    sub *= 10_i64.pow((input.n as i64 - perm) as u32 + 2);
+                    _cache_prepare_score[i][j] -= add - sub;
+                }
+            }
+        }
+
+        Self {
+            cache_prepare_score: _cache_prepare_score,
+        }
+    }
+}
+impl Evaluator for Potential {
+    fn evaluate(&self, state: &Terminal<Self>) -> i64 {
+        let mut score = 0;
+        for i in 0..state.h {
+            for j in 0..state.w {
+                for k in 0..2 {
+                    let cont = state.grid_cont[i][j][k];
+                    if cont != -1 {
+                        let gx = cont / state.h as i64;
+                        let mut add = 0;
+                        // x 方向の寄与
+                        add += (i as i64 - gx) * (i as i64 - gx);
+                        // y 方向の寄与
+                        add +=
+                            (j as i64 - (state.w - 1) as i64) * (j as i64 - (state.w - 1) as i64);
+                        // 倍率
+                        add *= self.cont_weight(state, cont);
+                        score += add;
+                    }
+                }
+            }
+        }
+        // 盤面に存在しないコンテナの距離を考慮
+        for i in 0..state.h {
+            for j in state.incoming_cont_idx[i]..state.w {
+                let cont = state.conts[i][j];
+                let gx = cont / state.h as i64;
+
+                let mut add = 0;
+                // x 方向の寄与
+                add += (i as i64 - gx) * (i as i64 - gx);
+                // y 方向の寄与
+                add += (-(j as i64 - state.incoming_cont_idx[i] as i64 + 1) - (state.w - 1) as i64)
+                    * (-(j as i64 - state.incoming_cont_idx[i] as i64 + 1) - (state.w - 1) as i64);
+                // 倍率
+                add *= self.cont_weight(state, cont);
+                score += add;
+            }
+        }
+        if state.out_cnt != state.h * state.w {
+            score += 1;
+        }
+        score
+    }
+
+    fn shift_diff(&self, state: &Terminal<Self>, c: usize, dir: usize) -> i64 {
+        let crane = &state.cranes[c];
+        if !crane.suspended {
+            return 0;
+        }
+
+        let (px, py) = (crane.x as i64, crane.y as i64);
+        let (nx, ny) = (px + DX[dir] as i64, py + DY[dir] as i64);
+
+        let cont = state.grid_cont[px as usize][py as usize][crane.big as usize];
+        let (gx, gy) = (cont / state.h as i64, state.w as i64 - 1);
+
+        let mut sub: i64 = 0;
+        let mut add: i64 = 0;
+
+        // x 方向の寄与
+        sub += (px - gx) * (px - gx);
+        add += (nx - gx) * (nx - gx);
+        // y 方向の寄与
+        sub += (py - gy) * (py - gy);
+        add += (ny - gy) * (ny - gy);
+        // 倍率
+        sub *= self.cont_weight(state, cont);
+        add *= self.cont_weight(state, cont);
+        add - sub
+    }
+
+    fn carry_in_diff(&self, state: &Terminal<Self>, i: usize) -> i64 {
+        self.cache_prepare_score[i][state.incoming_cont_idx[i]]
+    }
+
+    fn carry_out_diff(&self, _state: &Terminal<Self>, _i: usize, _cont: i64) -> i64 {
+        // 搬出口では距離が 0 なので変わらない
+        0
+    }
+
+    fn cont_weight(&self, state: &Terminal<Self>, cont: i64) -> i64 {
+        let perm = cont % state.h as i64;
+        10_i64.pow((state.w as i64 - perm) as u32 + 2)
+    }
+
+    // 待つ・無駄に動くと、最も急ぐコンテナ (各搬出口が次に搬出すべきものの中で重みが最大のもの) を 1 ターン分近づけられない
+    fn turn_cost(&self, state: &Terminal<Self>) -> i64 {
+        (0..state.h)
+            .filter(|&g| state.out_cont_idx[g] < (g + 1) * state.h)
+            .map(|g| self.cont_weight(state, state.out_cont_idx[g] as i64))
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+/*
+    ジャッジの得点に合わせた評価関数 (単位は、最初に搬出すべきコンテナを 1 マス運ぶ 1 ターン分の ORDER_BASE^N)
+    未搬出のコンテナは 搬出口までのマンハッタン距離 + 降ろす 1 ターン に、搬出順が早いほど大きい重み ORDER_BASE^{N - 何番目に搬出すべきか} を掛ける
+    搬入前のコンテナは搬入口の左に並んでいるものとし、順番違い・誤った搬出口での搬出 (SolverConfig::penalty) はジャッジと同じく 1 個につき 100 ターン・10^4 ターン分とする
+*/
+#[derive(Clone, Default, PartialEq)]
+pub struct ScoreAware;
+impl ScoreAware {
+    pub const ORDER_BASE: i64 = 16; // 搬出順の重みの底
+    pub const INVERSION: i64 = 100; // 転倒 1 個あたりのターン数 (ジャッジの B の係数)
+    pub const WRONG_GATE: i64 = 10_000; // 誤った搬出口 1 個あたりのターン数 (ジャッジの C の係数)
+
+    /* 1 ターン分の評価値 */
+    fn turn(state: &Terminal<Self>) -> i64 {
+        Self::ORDER_BASE.pow(state.w as u32)
+    }
+
+    /* (x, y) にあるコンテナ cont を搬出するまでの評価値 */
+    fn cost(&self, state: &Terminal<Self>, cont: i64, x: usize, y: i64) -> i64 {
+        let gx = cont as usize / state.h;
+        let dist = (x.abs_diff(gx) as i64) + (state.w as i64 - 1 - y);
+        self.cont_weight(state, cont) * (dist + 1)
+    }
+}
+impl Evaluator for ScoreAware {
+    fn evaluate(&self, state: &Terminal<Self>) -> i64 {
+        let mut score = Self::turn(state)
+            * (Self::INVERSION * state.inversions as i64
+                + Self::WRONG_GATE * state.wrong_gate as i64);
+        for i in 0..state.h {
+            for j in 0..state.w {
+                for &cont in state.grid_cont[i][j].iter().filter(|&&cont| cont != -1) {
+                    score += self.cost(state, cont, i, j as i64);
+                }
+            }
+            for j in state.incoming_cont_idx[i]..state.w {
+                let y = -((j - state.incoming_cont_idx[i]) as i64 + 1);
+                score += self.cost(state, state.conts[i][j], i, y);
+            }
+        }
+        score
+    }
+
+    fn shift_diff(&self, state: &Terminal<Self>, c: usize, dir: usize) -> i64 {
+        let crane = &state.cranes[c];
+        if !crane.suspended {
+            return 0;
+        }
+        let (x, y) = (crane.x, crane.y);
+        let (nx, ny) = ((x as isize + DX[dir]) as usize, y as isize + DY[dir]);
+        let cont = state.grid_cont[x][y][crane.big as usize];
+        self.cost(state, cont, nx, ny as i64) - self.cost(state, cont, x, y as i64)
+    }
+
+    fn carry_in_diff(&self, state: &Terminal<Self>, i: usize) -> i64 {
+        // 搬入口に並んでいるコンテナが全て 1 マスずつ搬出口に近づく
+        -state.conts[i][state.incoming_cont_idx[i]..]
+            .iter()
+            .map(|&cont| self.cont_weight(state, cont))
+            .sum::<i64>()
+    }
+
+    fn carry_out_diff(&self, state: &Terminal<Self>, i: usize, cont: i64) -> i64 {
+        let (inversions, wrong_gate) = state.dispatch_penalty(i, cont);
+        Self::turn(state)
+            * (Self::INVERSION * inversions as i64 + Self::WRONG_GATE * wrong_gate as i64)
+            - self.cost(state, cont, i, state.w as i64 - 1)
+    }
+
+    fn cont_weight(&self, state: &Terminal<Self>, cont: i64) -> i64 {
+        Self::ORDER_BASE.pow((state.w - cont as usize % state.h) as u32)
+    }
+
+    fn turn_cost(&self, state: &Terminal<Self>) -> i64 {
+        Self::turn(state)
+    }
+}
+
+// 同時操作でのクレーン 1 台分の候補 (操作, 移動先, 評価値の差分)
+pub type JointOption = (usize, (usize, usize), i64);
+
+/* 経過時間を見てビーム幅を調整する構造体 */
+pub struct TimeKeeper {
+    pub start: std::time::Instant,
+    pub limit: f64,
+    pub min_width: usize,
+    pub max_width: usize,
+    pub expected_layers: usize,
+    pub layer_start: f64,
+}
+impl TimeKeeper {
+    pub fn new(config: &SolverConfig, expected_layers: usize) -> Self {
+        Self {
+            start: std::time::Instant::now(),
+            limit: config.search_time(),
+            min_width: config.min_width,
+            max_width: config.max_width,
+            expected_layers,
+            layer_start: 0.0,
+        }
+    }
+
+    pub fn elapsed(&self) -> f64 {
+        self.start.elapsed().as_secs_f64()
+    }
+
+    /* 直前の層にかかった時間から、残りの見込みの層数を制限時間内に終えられるビーム幅を返す */
+    pub fn next_width(&mut self, width: usize, layer: usize) -> usize {
+        let now = self.elapsed();
+        let spent = (now - self.layer_start).max(1e-6);
+        self.layer_start = now;
+
+        // 見込みより長引いている場合も、残り 1 割の層数はあるものとして幅を絞る
+        let remaining_layers = self
+            .expected_layers
+            .saturating_sub(layer + 1)
+            .max(self.expected_layers / 10)
+            .max(1);
+        let remaining_time = (self.limit - now).max(0.0);
+        let target = remaining_time / remaining_layers as f64;
+        let ratio = (target / spent).clamp(0.5, 2.0);
+
+        // min_width でも残りの層が時間内に終わらない見込みなら、min_width より絞る
+        let min_width_time = spent / width as f64 * self.min_width as f64 * remaining_layers as f64;
+        let min_width = if min_width_time <= remaining_time {
+            self.min_width
+        } else {
+            1
+        };
+        ((width as f64 * ratio) as usize).clamp(min_width, self.max_width)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Cand {
+    pub op: usize,
+    pub parent: usize,
+    pub eval_score: i64,
+    pub hash: u64,  // 遷移後の盤面の hash (搬入・搬出前)
+    pub done: bool, // 遷移前の盤面で全て搬出済みか
+}
+impl Cand {
+    pub fn to_node(&self) -> Node {
+        Node {
+            child: !0,
+            prev: !0,
+            next: !0,
+            op: self.op,
+            parent: self.parent,
+        }
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct Node {
+    pub op: usize,
+    pub parent: usize, // 親Node
+    pub child: usize,  // 代表の子Node
+    pub prev: usize,   // 前の兄弟Node
+    pub next: usize,   // 次の兄弟Node
+}
+
+pub struct BeamSearch<E = Potential> {
+    pub state: Terminal<E>,
+    pub leaf: Vec<usize>, // 子が存在しないNodeのindex
+    pub next_leaf: Vec<usize>,
+    pub nodes: Vec<Node>, // 足りなくなったら伸ばすので、長さは同時に使ったノード数の最大値になる
+    pub cur_node: usize,
+    pub free: Vec<usize>,                     // nodesのうち使われていないindex
+    pub seen: std::collections::HashSet<u64>, // update で採用した候補の hash
+}
+impl<E: Evaluator> BeamSearch<E> {
+    /* [rhooさんの記事](https://qiita.com/rhoo/items/2f647e32f6ff2c6ee056)を参考 */
+    pub fn new(state: Terminal<E>, node: Node, config: &SolverConfig) -> Self {
+        // ノードは消したものを使い回し、足りない分だけ確保する
+        let mut nodes = Vec::with_capacity(config.max_width * INITIAL_NODE_LAYERS);
+        nodes.push(node);
+
+        BeamSearch {
+            state,
+            nodes,
+            free: vec![],
+            leaf: vec![0],
+            next_leaf: vec![],
+            cur_node: 0,
+            seen: std::collections::HashSet::new(),
+        }
+    }
+
+    // 頂点を新たに追加して次の層の葉にする
+    pub fn add_node(&mut self, cand: Cand) {
+        let new = self.new_node(cand);
+        self.next_leaf.push(new);
+    }
+
+    // 頂点を新たに追加してその index を返す
+    // 代表の子 Node の前に挿入する形で実装
+    pub fn new_node(&mut self, cand: Cand) -> usize {
+        let next = self.nodes[cand.parent].child;
+        let new = self.free.pop().unwrap_or_else(|| {
+            self.nodes.push(Node::default());
+            self.nodes.len() - 1
+        });
+        if next != !0 {
+            self.nodes[next].prev = new;
+        }
+        self.nodes[cand.parent].child = new;
+
+        self.nodes[new] = Node {
+            next,
+            ..cand.to_node()
+        };
+        new
+    }
+
+    // 既に探索済みのノードで葉のノードを再帰的に消していく
+    pub fn del_node(&mut self, mut idx: usize) {
+        loop {
+            self.free.push(idx);
+            let Node {
+                prev, next, parent, ..
+            } = self.nodes[idx];
+            // assert_ne!(parent, !0, "全てのノードを消そうとしています");
+            // 兄弟がいないなら親を消しに行く
+            if prev & next == !0 {
+                idx = parent;
+                continue;
+            }
+            if prev != !0 {
+                self.nodes[prev].next = next;
+            } else {
+                self.nodes[parent].child = next;
+            }
+            if next != !0 {
+                self.nodes[next].prev = prev;
+            }
+            break;
+        }
+    }
+
+    // 走査の非再帰実装
+    pub fn no_dfs(&mut self, cands: &mut Vec<Cand>) {
+        // 1本道でなくなるまで潜る
+        loop {
+            let Node { next, child, .. } = self.nodes[self.cur_node];
+            if next == !0 || child == !0 {
+                break;
+            }
+            self.cur_node = child;
+            self.state.apply(&self.nodes[self.cur_node]);
+        }
+
+        let root = self.cur_node;
+        loop {
+            let child = self.nodes[self.cur_node].child;
+            if child == !0 {
+                #[cfg(debug_assertions)]
+                let start = cands.len();
+                self.append_cands(self.cur_node, cands);
+                #[cfg(debug_assertions)]
+                for cand in cands[start..].iter() {
+                    self.state.check_revert(&cand.to_node());
+                }
+                loop {
+                    if self.cur_node == root {
+                        return;
+                    }
+                    let node = &self.nodes[self.cur_node];
+                    self.state.revert();
+                    if node.next != !0 {
+                        self.cur_node = node.next;
+                        self.state.apply(&self.nodes[self.cur_node]);
+                        break;
+                    }
+                    self.cur_node = node.parent;
+                }
+            } else {
+                self.cur_node = child;
+                self.state.apply(&self.nodes[self.cur_node]);
+            }
+        }
+    }
+
+    pub fn enum_cands(&mut self, cands: &mut Vec<Cand>) {
+        self.no_dfs(cands);
+    }
+
+    // 評価値順に並んだ cands から、同じ盤面になるものを除いて上位 width 個を採用する
+    // 除いた候補の数を返す
+    pub fn update<I: Iterator<Item = Cand>>(&mut self, cands: I, width: usize) -> usize {
+        self.next_leaf.clear();
+        self.seen.clear();
+        let mut duplicated = 0;
+        for cand in cands {
+            if self.seen.len() == width {
+                break;
+            }
+            if !self.seen.insert(cand.hash) {
+                duplicated += 1;
+                continue;
+            }
+            self.add_node(cand);
+        }
+
+        for i in 0..self.leaf.len() {
+            let n = self.leaf[i];
+            // 子が存在しないノードは無駄なので消す
+            if self.nodes[n].child == !0 {
+                self.del_node(n);
+            }
+        }
+
+        std::mem::swap(&mut self.leaf, &mut self.next_leaf);
+        duplicated
+    }
+
+    /*
+        1 層ずつ幅 width のビームサーチをする関数 (幅は残り時間に合わせて調整する)
+        全て搬出できたらそのノードを、turn 層・search_time 秒以内に終わらなければ評価値順に並んだ最後の層の葉を返す
+    */
+    pub fn beam(
+        &mut self,
+        config: &SolverConfig,
+        time_keeper: &mut TimeKeeper,
+    ) -> Result<usize, Vec<usize>> {
+        let mut best_idx: usize = !0;
+        let mut width = config.max_width;
+        let mut layers = 0;
+        let mut expanded_nodes = 0;
+        let mut duplicated = 0;
+
+        for layer in 0..config.turn {
+            // eprintln!("turn: {}", turn);
+            layers += 1;
+            expanded_nodes += self.leaf.len();
+            let mut cands = Vec::new();
+            self.enum_cands(&mut cands);
+            cands.sort_by_key(|a| a.eval_score);
+
+            // cands の top 3 の候補を表示
+            // for cand in cands.iter().take(3) {
+            //     eprintln!("Score: {}, op: {}", cand.eval_score, OP[cand.op]);
+            // }
+            // eprintln!("candidates: {}\n", self.leaf.len());
+
+            // 最も良い盤面で全て搬出済みになった場合に終了
+            // (ペナルティを許す場合は、先に搬出し終えた盤面より後から搬出し終える盤面の方が良いことがある)
+            // assert!(!cands.is_empty());
+            if cands[0].done {
+                best_idx = cands[0].parent;
+                break;
+            }
+
+            // 候補を基に次の状態を更新 (同じ盤面は 1 つにまとめる)
+            duplicated += self.update(cands.into_iter(), width);
+
+            // 制限時間を過ぎたら、この層の葉を途中の出力として返す
+            if time_keeper.elapsed() >= config.search_time() {
+                break;
+            }
+            width = time_keeper.next_width(width, layer);
+        }
+        eprintln!(
+            "layers: {}, expanded nodes: {}, duplicated: {}, last width: {}, peak nodes: {} ({:.1} MB), elapsed: {:.3}s",
+            layers,
+            expanded_nodes,
+            duplicated,
+            width,
+            self.nodes.len(),
+            (self.nodes.len() * std::mem::size_of::<Node>()) as f64 / 1e6,
+            time_keeper.elapsed()
+        );
+
+        if best_idx == !0 {
+            return Err(self.leaf.iter().take(PARTIAL_LEAVES).copied().collect());
+        }
+        Ok(best_idx)
+    }
+
+    /*
+        chokudai サーチ: 深さ (層) ごとに候補の優先度付きキューを持ち、時間の許す限り
+        浅い方から順に各深さで評価値の良い候補を chokudai_width 個ずつ展開することを繰り返す
+        候補は取り出した時に初めてノードにし、self.state は goto で apply / revert してノード間を移動させる
+        全て搬出できたらそのノードを、できなければ最も深い層で評価値の良い候補の親を返す
+    */
+    pub fn chokudai(
+        &mut self,
+        config: &SolverConfig,
+        time_keeper: &TimeKeeper,
+    ) -> Result<usize, Vec<usize>> {
+        type Entry = (std::cmp::Reverse<i64>, usize, usize, u64); // (評価値, 親, 操作, hash)
+        let entry = |cand: Cand| {
+            (
+                std::cmp::Reverse(cand.eval_score),
+                cand.parent,
+                cand.op,
+                cand.hash,
+            )
+        };
+        let depth = config.turn;
+        let mut heaps: Vec<std::collections::BinaryHeap<Entry>> =
+            vec![Default::default(); depth + 1];
+        let mut seen: Vec<std::collections::HashSet<u64>> = vec![Default::default(); depth + 1];
+        let mut path = vec![0]; // 根から self.state が表すノードまでの経路
+        let mut cands = vec![];
+        let mut batch = vec![];
+        // 全て搬出できた盤面の (評価値, 深さ, ノード)
+        let mut best: Option<(i64, usize, usize)> = None;
+        let mut sweeps = 0;
+        let mut expanded_nodes = 0;
+        let mut duplicated = 0;
+
+        self.append_cands(0, &mut cands);
+        heaps[1].extend(cands.drain(..).map(entry));
+        while time_keeper.elapsed() < config.search_time() {
+            sweeps += 1;
+            let mut progressed = false;
+            for d in 1..=depth {
+                // 全て搬出できた盤面より深い候補は展開しない
+                if best.is_some_and(|(_, best_depth, _)| d >= best_depth) {
+                    break;
+                }
+
+                // 評価値の良い候補から、この深さで展開済みの盤面を除いて chokudai_width 個を取り出す
+                while batch.len() < config.chokudai_width {
+                    let Some((std::cmp::Reverse(eval_score), parent, op, hash)) = heaps[d].pop()
+                    else {
+                        break;
+                    };
+                    if !seen[d].insert(hash) {
+                        duplicated += 1;
+                        continue;
+                    }
+                    batch.push(Cand {
+                        op,
+                        parent,
+                        eval_score,
+                        hash,
+                        done: false,
+                    });
+                }
+                // 兄弟を続けて展開すると goto の移動が短く済む
+                batch.sort_by_key(|cand| cand.parent);
+
+                for cand in batch.drain(..) {
+                    progressed = true;
+                    let idx = self.new_node(cand);
+                    self.goto(&mut path, idx, d);
+                    if self.state.out_cnt == self.state.h * self.state.w {
+                        // ペナルティを許す場合は、後から搬出し終える盤面の方が良いことがある
+                        let key = (self.state.score, d);
+                        let better = match best {
+                            Some((score, best_depth, _)) => key < (score, best_depth),
+                            None => true,
+                        };
+                        if better {
+                            best = Some((self.state.score, d, idx));
+                        }
+                        continue;
+                    }
+                    if d < depth {
+                        expanded_nodes += 1;
+                        self.append_cands(idx, &mut cands);
+                        heaps[d + 1].extend(cands.drain(..).map(entry));
+                    }
+                }
+
+                // 溜まりすぎた候補は評価値の良い max_width 個だけ残す
+                if d < depth && heaps[d + 1].len() > 2 * config.max_width {
+                    let mut entries = std::mem::take(&mut heaps[d + 1]).into_sorted_vec();
+                    entries.drain(..entries.len() - config.max_width);
+                    heaps[d + 1] = entries.into();
+                }
+            }
+            // 展開できる候補が残っていない
+            if !progressed {
+                break;
+            }
+        }
+        eprintln!(
+            "sweeps: {}, expanded nodes: {}, duplicated: {}, peak nodes: {} ({:.1} MB), elapsed: {:.3}s",
+            sweeps,
+            expanded_nodes,
+            duplicated,
+            self.nodes.len(),
+            (self.nodes.len() * std::mem::size_of::<Node>()) as f64 / 1e6,
+            time_keeper.elapsed()
+        );
+
+        if let Some((_, _, idx)) = best {
+            return Ok(idx);
+        }
+        let mut leaves = vec![];
+        if let Some(d) = heaps.iter().rposition(|heap| !heap.is_empty()) {
+            // into_sorted_vec は評価値の悪い順
+            let mut entries = std::mem::take(&mut heaps[d]).into_sorted_vec();
+            while let Some((_, parent, _, _)) = entries.pop() {
+                if leaves.len() == PARTIAL_LEAVES {
+                    break;
+                }
+                if !leaves.contains(&parent) {
+                    leaves.push(parent);
+                }
+            }
+        }
+        if leaves.is_empty() {
+            leaves.push(0);
+        }
+        Err(leaves)
+    }
+
+    // self.state を path (path[k] は深さ k のノード) の末尾のノードから深さ depth の target のノードが表す状態に移す
+    // 共通の祖先まで revert してから target まで apply する
+    pub fn goto(&mut self, path: &mut Vec<usize>, target: usize, depth: usize) {
+        let mut chain = vec![];
+        let mut idx = target;
+        let mut d = depth;
+        while d >= path.len() || path[d] != idx {
+            chain.push(idx);
+            idx = self.nodes[idx].parent;
+            d -= 1;
+        }
+        for _ in d + 1..path.len() {
+            self.state.revert();
+        }
+        path.truncate(d + 1);
+        for &idx in chain.iter().rev() {
+            self.state.apply(&self.nodes[idx]);
+            path.push(idx);
+        }
+    }
+
+    pub fn restore(&self, mut idx: usize) -> Vec<usize> {
+        let mut ret = vec![];
+        loop {
+            let Node { op, parent, .. } = self.nodes[idx];
+            if op == !0 {
+                break;
+            }
+            ret.push(op);
+            idx = parent;
+        }
+
+        ret.reverse();
+        ret
+    }
+
+    // self.state が self.nodes[idx] のノードが表す状態になっている
+    // self.nodes[idx] からの Cand を cands に積む
+    pub fn append_cands(&mut self, idx: usize, cands: &mut Vec<Cand>) {
+        if self.state.joint_move {
+            self.append_joint_cands(idx, cands);
+            return;
+        }
+        // assert_eq!(node.child, !0);
+        let c = self.state.moving_crane();
+        let done = self.state.out_cnt == self.state.h * self.state.w;
+
+        if self.state.cranes[c].exploded {
+            // 爆破済みのクレーンの手番は停止しかない
+            cands.push(Cand {
+                op: Operation::Stop as usize,
+                parent: idx,
+                eval_score: self.state.score,
+                hash: self.state.hash,
+                done,
+            });
+            return;
+        }
+
+        let idle_costs = self.state.idle_costs(c);
+        for (_op, &idle_cost) in idle_costs.iter().enumerate() {
+            // 行動可能かを check
+            if !self.state.cranes[c].action_ok(
+                _op,
+                &self.state.grid_crane,
+                &self.state.grid_cont,
+                &self.state.out_cont_idx,
+            ) {
+                continue;
+            }
+
+            // 前回の逆操作は無視 (待機は続けてもよい)
+            if _op != Operation::Stop as usize
+                && reverse_op(_op) as usize == self.state.cranes[c].pre_op
+            {
+                continue;
+            }
+
+            // 盤面評価値を計算（差分計算で求める）
+            let state = &self.state;
+            let mut score = state.score;
+            if _op < 4 && state.cranes[c].suspended {
+                let crane = &state.cranes[c];
+                let (px, py) = (crane.x, crane.y);
+                score += state.evaluator.shift_diff(state, c, _op);
+
+                // right && py == 0 の時は搬入口から出るコンテナの評価値変動も考慮
+                if py == 0 && _op == 0 {
+                    score += state.evaluator.carry_in_diff(state, px);
+                }
+                // assert!(score >= 0, "score is negative. score: {}", score);
+            }
+            // 搬出口で降ろす時は搬出による評価値変動も考慮
+            if _op == Operation::Lower as usize && state.cranes[c].y == state.w - 1 {
+                let crane = &state.cranes[c];
+                let cont = state.grid_cont[crane.x][crane.y][crane.big as usize];
+                score += state.evaluator.carry_out_diff(state, crane.x, cont);
+            }
+            score += idle_cost;
+            if _op == Operation::Bomb as usize {
+                score += state.evaluator.bomb_cost(state);
+            }
+            cands.push(Cand {
+                op: _op,
+                parent: idx,
+                eval_score: score,
+                hash: self.state.hash ^ self.state.action_hash_diff(c, _op),
+                done,
+            });
+        }
+    }
+
+    // self.nodes[idx] からの全クレーン同時操作の Cand を cands に積む
+    // 評価値の良い JOINT_CANDS 個だけを残す
+    pub fn append_joint_cands(&mut self, idx: usize, cands: &mut Vec<Cand>) {
+        let state = &self.state;
+        let n = state.cranes.len();
+
+        // 吊り上げに向かうべきコンテナの位置
+        // 大クレーンは今すぐ搬出可能なもの、小クレーンはそれに加えて搬入口を塞いでいるもの
+        let mut big_targets = vec![];
+        let mut small_targets = vec![];
+        for x in 0..state.h {
+            for y in 0..state.w - 1 {
+                if !state.cont_on_ground(x, y) {
+                    continue;
+                }
+                if state
+                    .out_cont_idx
+                    .contains(&(state.grid_cont[x][y][0] as usize))
+                {
+                    big_targets.push((x, y));
+                    small_targets.push((x, y));
+                } else if y == 0 {
+                    small_targets.push((x, y));
+                }
+            }
+        }
+
+        // クレーンごとに (操作, 移動先, 評価値の差分) を列挙
+        let mut options: Vec<Vec<JointOption>> = vec![vec![]; n];
+        for (c, crane) in state.cranes.iter().enumerate() {
+            // 今すぐ搬出できるコンテナを持った小クレーンの、コンテナを避けた搬出口までの距離
+            let gate_dist = if !crane.big && crane.suspended {
+                let cont = state.grid_cont[crane.x][crane.y][0];
+                let gate = cont as usize / state.h;
+                (state.out_cont_idx[gate] == cont as usize).then(|| (gate, state.gate_dist(gate)))
+            } else {
+                None
+            };
+            // 搬出口への道がコンテナで塞がれていて、遠回りが必要か
+            let blocked = |(gate, gate_dist): &(usize, Vec<Vec<usize>>), x: usize, y: usize| {
+                gate_dist[x][y] != gate.abs_diff(x) + (state.w - 1 - y)
+            };
+            let stuck = gate_dist
+                .as_ref()
+                .is_some_and(|g| blocked(g, crane.x, crane.y));
+            for op in 0..OP_NUM {
+                // 前回の逆操作は無視
+                if op != Operation::Stop as usize && reverse_op(op) as usize == crane.pre_op {
+                    continue;
+                }
+                // 道が塞がれた小クレーンは、1 列目でも降ろして大クレーンに任せられる
+                let stuck_lower =
+                    op == Operation::Lower as usize && stuck && crane.y == 1 && state.w > 2;
+                if !state.joint_action_ok(c, op) && !stuck_lower {
+                    continue;
+                }
+                // 爆破済み・爆破するクレーンは盤面から消えるので、衝突判定の対象外
+                if crane.exploded || op == Operation::Bomb as usize {
+                    let diff = if op == Operation::Bomb as usize {
+                        state.evaluator.bomb_cost(state)
+                    } else {
+                        0
+                    };
+                    options[c].push((op, (!0, !0), diff));
+                    continue;
+                }
+                let (x, y) = if op < 4 {
+                    (
+                        (crane.x as isize + DX[op]) as usize,
+                        (crane.y as isize + DY[op]) as usize,
+                    )
+                } else {
+                    (crane.x, crane.y)
+                };
+
+                let mut diff = 0;
+                if op < 4 && crane.suspended {
+                    diff += state.evaluator.shift_diff(state, c, op);
+                    // 搬入口から離れる時は搬入されるコンテナの評価値変動も考慮
+                    if crane.y == 0 && !state.cont_on_ground(crane.x, 0) {
+                        diff += state.evaluator.carry_in_diff(state, crane.x);
+                    }
+                }
+                // 搬出口で降ろす時は搬出による評価値変動も考慮
+                if op == Operation::Lower as usize && crane.y == state.w - 1 {
+                    let cont = state.grid_cont[crane.x][crane.y][crane.big as usize];
+                    diff += state.evaluator.carry_out_diff(state, crane.x, cont);
+                }
+
+                // 何も吊り上げていないクレーンは、吊り上げ可能なコンテナへの距離で誘導
+                // 搬出口の上で吊り上げたままの場合は搬出を促すために誘導の最大値より大きいコストを課す
+                // 小クレーンが降ろせる場所ですぐには搬出できないコンテナを持ち続けるのは抑制
+                let suspended = (crane.suspended || op == 4) && op != 5;
+                if suspended {
+                    let cont = if crane.suspended {
+                        state.grid_cont[crane.x][crane.y][crane.big as usize]
+                    } else {
+                        state.grid_cont[crane.x][crane.y][0]
+                    };
+                    if (x, y) == (cont as usize / state.h, state.w - 1) {
+                        diff += (state.h + state.w) as i64;
+                    }
+                    if !crane.big
+                        && y >= 2
+                        && state.out_cont_idx[cont as usize / state.h] != cont as usize
+                    {
+                        diff += state.evaluator.cont_weight(state, cont) * HOLD_PENALTY;
+                    }
+                    // 小クレーンはコンテナの上を通れないので、搬出口への道が塞がれている場合は降ろして大クレーンに任せるよう促す
+                    if gate_dist.as_ref().is_some_and(|g| blocked(g, x, y)) {
+                        diff += state.evaluator.cont_weight(state, cont) * HOLD_PENALTY;
+                    }
+                } else {
+                    let targets = if crane.big {
+                        &big_targets
+                    } else {
+                        &small_targets
+                    };
+                    let dist = |(x, y): (usize, usize)| {
+                        targets
+                            .iter()
+                            .map(|&(tx, ty)| (tx.abs_diff(x) + ty.abs_diff(y)) as i64)
+                            .min()
+                    };
+                    // 目標に近づかない移動は、隣接する他のクレーンの邪魔になりうる場合のみ考慮
+                    if op < 4
+                        && !crane.suspended
+                        && dist((x, y)) >= dist((crane.x, crane.y))
+                        && !state.cranes.iter().any(|other| {
+                            !other.exploded
+                                && other.x.abs_diff(crane.x) + other.y.abs_diff(crane.y) == 1
+                        })
+                    {
+                        continue;
+                    }
+                    diff += dist((x, y)).unwrap_or(0);
+                }
+                options[c].push((op, (x, y), diff));
+            }
+        }
+
+        // クレーン同士の衝突 (同じマスへの移動・すれ違い) が無い組み合わせのうち、評価値の良い JOINT_CANDS 個を探す
+        // 差分の小さい順に試し、残りのクレーンの差分の最小値を足しても上位に入らなければ打ち切る
+        for o in options.iter_mut() {
+            o.sort_by_key(|&(_, _, diff)| diff);
+        }
+        let mut rest = vec![0; n + 1];
+        for c in (0..n).rev() {
+            rest[c] = rest[c + 1] + options[c].first().map_or(0, |&(_, _, diff)| diff);
+        }
+        let mut joint = std::collections::BinaryHeap::new();
+        let mut ops = vec![0; n];
+        let mut dest = vec![(0, 0); n];
+        Self::dfs_joint(
+            state,
+            &options,
+            &rest,
+            0,
+            state.score,
+            &mut ops,
+            &mut dest,
+            &mut joint,
+        );
+        let mut joint: Vec<(i64, usize)> = joint.into_sorted_vec();
+        if joint.is_empty() {
+            // 動けるクレーンが無い場合 (全て搬出済みの場合など) は仕方なく全クレーン停止
+            ops.fill(Operation::Stop as usize);
+            let diff: i64 = options
+                .iter()
+                .flat_map(|o| {
+                    o.iter()
+                        .filter(|&&(op, _, _)| op == Operation::Stop as usize)
+                })
+                .map(|&(_, _, diff)| diff)
+                .sum();
+            joint.push((state.score + diff, encode_joint(&ops)));
+        }
+
+        let done = state.out_cnt == state.h * state.w;
+        for (eval_score, op) in joint {
+            let hash = state.hash ^ state.joint_hash_diff(&decode_joint(op, n));
+            cands.push(Cand {
+                op,
+                parent: idx,
+                eval_score,
+                hash,
+                done,
+            });
+        }
+    }
+
+    // options[c] は差分の昇順、rest[c] はクレーン c 以降の差分の最小値の和
+    // joint には評価値の良い JOINT_CANDS 個までの (評価値, 操作) を最大ヒープで持つ
+    #[allow(clippy::too_many_arguments)]
+    pub fn dfs_joint(
+        state: &Terminal<E>,
+        options: &[Vec<JointOption>],
+        rest: &[i64],
+        c: usize,
+        score: i64,
+        ops: &mut Vec<usize>,
+        dest: &mut Vec<(usize, usize)>,
+        joint: &mut std::collections::BinaryHeap<(i64, usize)>,
+    ) {
+        if c == options.len() {
+            // 全クレーンが停止する遷移は無駄なので除く
+            if ops.iter().all(|&op| op == Operation::Stop as usize) {
+                return;
+            }
+            joint.push((score, encode_joint(ops)));
+            if joint.len() > JOINT_CANDS {
+                joint.pop();
+            }
+            return;
+        }
+        let pos = |j: usize| (state.cranes[j].x, state.cranes[j].y);
+        for &(op, to, diff) in options[c].iter() {
+            if joint.len() == JOINT_CANDS && score + diff + rest[c + 1] >= joint.peek().unwrap().0 {
+                break;
+            }
+            if to != (!0, !0)
+                && (0..c).any(|j| dest[j] == to || (dest[j] == pos(c) && to == pos(j)))
+            {
+                continue;
+            }
+            ops[c] = op;
+            dest[c] = to;
+            Self::dfs_joint(state, options, rest, c + 1, score + diff, ops, dest, joint);
+        }
+    }
+}
+
+/*
+    タスク単位のプランナー
+    「どのコンテナを・どのクレーンで・どこへ運ぶか」を決め、クレーンの経路を引いて運ぶ
+      run: 時空間の予約表 (Reservation) を使った協調 A* で、手の空いたクレーンから順に計画を立てて全クレーンを同時に動かす
+      run_sequential: 1 台ずつ動かし、他のクレーンを止まった障害物として grid_crane 上の BFS で経路を引く
+    ビームサーチより弱いが速く、ビームサーチが turn 層以内に終わらなかった場合の代わりにも使う
+*/
+#[derive(Clone)]
+pub struct Planner {
+    pub state: Terminal,
+    pub ops: Vec<Vec<usize>>,     // ターンごとの各クレーンの操作
+    pub to_bomb: Vec<usize>,      // 次のターンに爆破するクレーン
+    pub dispatched: Vec<bool>,    // 各コンテナを搬出したか
+    pub out_order: Vec<Vec<i64>>, // 搬出口ごとの搬出したコンテナの順番
+    pub res: Reservation,         // 各クレーンのこれからの位置
+    pub plans: Vec<std::collections::VecDeque<usize>>, // 各クレーンのこれからの操作
+    pub tasks: Vec<Option<Task>>, // 各クレーンが実行中のタスク
+    pub loaded_cells: Vec<Vec<(usize, usize)>>, // 小クレーンが荷物を持って通る予定のマス
+    pub events: usize,            // タスクの割り当て・完了や搬入の回数
+    pub failed: Vec<usize>,       // 各クレーンがタスクを見つけられなかった時の events
+    pub deadline: Option<std::time::Instant>, // これを過ぎたら協調 A* (run) を打ち切る
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Task {
+    pub crane: usize,
+    pub from: (usize, usize), // 吊り上げる位置
+    pub to: (usize, usize),   // 吊り下げる位置
+}
+
+/* 時空間の予約表 (traj[c][k] は k ターン後のクレーン c の位置で、計画が終わった後は最後の位置にとどまる) */
+#[derive(Clone, Default)]
+pub struct Reservation {
+    pub traj: Vec<Vec<(usize, usize)>>, // 爆破したクレーンは空
+}
+impl Reservation {
+    pub fn pos(&self, c: usize, k: usize) -> Option<(usize, usize)> {
+        let traj = &self.traj[c];
+        traj.get(k).or(traj.last()).copied()
+    }
+
+    /* 予約されている最後のターン */
+    pub fn horizon(&self) -> usize {
+        self.traj.iter().map(|t| t.len()).max().unwrap_or(0)
+    }
+
+    /* クレーン c 以外の予約を (ターン, マス) ごとに展開する関数 */
+    pub fn occupancy(&self, c: usize, h: usize, w: usize) -> Occupancy {
+        let len = self.horizon().max(1);
+        let mut cells = vec![vec![!0; h * w]; len];
+        let mut last = vec![0; h * w];
+        for d in (0..self.traj.len()).filter(|&d| d != c) {
+            for (k, cells) in cells.iter_mut().enumerate() {
+                if let Some((x, y)) = self.pos(d, k) {
+                    cells[x * w + y] = d;
+                    let t = if k == len - 1 { !0 } else { k + 1 };
+                    last[x * w + y] = last[x * w + y].max(t);
+                }
+            }
+        }
+        Occupancy { cells, last, w }
+    }
+
+    /* 1 ターン進める */
+    pub fn advance(&mut self) {
+        for traj in self.traj.iter_mut().filter(|t| t.len() > 1) {
+            traj.remove(0);
+        }
+    }
+}
+
+/* 予約表を展開したもの (最後のターン以降は最後の位置にとどまる) */
+pub struct Occupancy {
+    pub cells: Vec<Vec<usize>>, // cells[k][x * w + y] は k ターン後にそのマスにいる他のクレーン (いなければ !0)
+    pub last: Vec<usize>, // 他のクレーンが最後にそのマスにいるターン + 1 (いなければ 0、ずっととどまるなら !0)
+    pub w: usize,
+}
+impl Occupancy {
+    fn at(&self, k: usize) -> &[usize] {
+        &self.cells[k.min(self.cells.len() - 1)]
+    }
+
+    /* k ターン後に from から to へ動くと他のクレーンとぶつかるか (from == to は待機) */
+    pub fn conflict(&self, k: usize, from: (usize, usize), to: (usize, usize)) -> bool {
+        let (now, next) = (self.at(k), self.at(k + 1));
+        let (from, to) = (from.0 * self.w + from.1, to.0 * self.w + to.1);
+        // 同じマスに入るか、すれ違う場合は衝突
+        next[to] != !0 || (now[to] != !0 && now[to] == next[from])
+    }
+
+    /* k ターン後以降に他のクレーンが cell を通らないか (計画を終えた後にとどまれるか) */
+    pub fn can_rest(&self, k: usize, cell: (usize, usize)) -> bool {
+        self.last[cell.0 * self.w + cell.1] <= k
+    }
+}
+
+impl Planner {
+    pub const MAX_TURN: usize = 10000;
+    pub const TRY_TASKS: usize = 4; // 1 台に割り当てを試すタスクの数
+
+    pub fn new(state: Terminal) -> Self {
+        let n = state.cranes.len();
+        let traj = state
+            .cranes
+            .iter()
+            .map(|c| if c.exploded { vec![] } else { vec![(c.x, c.y)] })
+            .collect();
+        Self {
+            dispatched: vec![false; state.h * state.w],
+            out_order: vec![vec![]; state.h],
+            res: Reservation { traj },
+            plans: vec![std::collections::VecDeque::new(); n],
+            tasks: vec![None; n],
+            loaded_cells: vec![vec![]; n],
+            events: 0,
+            failed: vec![!0; n],
+            deadline: None,
+            state,
+            ops: vec![],
+            to_bomb: vec![],
+        }
+    }
+
+    /* state から出力 ans を 1 ターンずつ再生したプランナー (続きを run / run_sequential で計画する) */
+    pub fn replay(state: Terminal, ans: &[String]) -> Self {
+        let mut planner = Self::new(state);
+        let ans: Vec<Vec<char>> = ans.iter().map(|a| a.chars().collect()).collect();
+        let t = ans.iter().map(|a| a.len()).max().unwrap_or(0);
+        for k in 0..t {
+            planner.step(Self::turn_ops(&ans, k));
+        }
+        // 予約表は今の位置から始める
+        for (c, crane) in planner.state.cranes.iter().enumerate() {
+            planner.res.traj[c] = if crane.exploded {
+                vec![]
+            } else {
+                vec![(crane.x, crane.y)]
+            };
+        }
+        planner
+    }
+
+    /* 出力 ans の k ターン目の全クレーンの操作 (出力が終わったクレーン (爆破済みを含む) は停止) */
+    pub fn turn_ops(ans: &[Vec<char>], k: usize) -> Vec<usize> {
+        ans.iter()
+            .map(|a| {
+                a.get(k).map_or(Operation::Stop as usize, |&ch| {
+                    OP.iter().position(|&op| op == ch).unwrap()
+                })
+            })
+            .collect()
+    }
+
+    /* deadline を過ぎたか */
+    pub fn timed_out(&self) -> bool {
+        self.deadline
+            .is_some_and(|deadline| std::time::Instant::now() >= deadline)
+    }
+
+    /* 全クレーンの操作 ops で 1 ターン進める関数 */
+    pub fn step(&mut self, mut ops: Vec<usize>) {
+        for c in std::mem::take(&mut self.to_bomb) {
+            ops[c] = Operation::Bomb as usize;
+            self.res.traj[c].clear();
+        }
+        // 搬出口に降ろしたコンテナはこのターンに搬出される
+        for (c, &op) in ops.iter().enumerate() {
+            let crane = &self.state.cranes[c];
+            if op == Operation::Lower as usize && crane.y == self.state.w - 1 {
+                let cont = self.state.grid_cont[crane.x][crane.y][crane.big as usize];
+                self.dispatched[cont as usize] = true;
+                self.out_order[crane.x].push(cont);
+            }
+        }
+        self.state.apply_joint(&ops);
+        self.ops.push(ops);
+        self.res.advance();
+    }
+
+    /* クレーン c に op をさせ、他のクレーンは止めて 1 ターン進める関数 */
+    pub fn step_one(&mut self, c: usize, op: usize) {
+        let mut ops = vec![Operation::Stop as usize; self.state.cranes.len()];
+        ops[c] = op;
+        self.step(ops);
+    }
+
+    /* 今の出力の得点 (tools の compute_score と同じ式) */
+    pub fn score(&self) -> i64 {
+        let h = self.state.h as i64;
+        let mut inversions = 0;
+        let mut wrong_gate = 0;
+        for (g, order) in self.out_order.iter().enumerate() {
+            // 転倒数は正しい搬出口から出たもの同士で数える
+            let order: Vec<i64> = order
+                .iter()
+                .copied()
+                .filter(|&a| a / h == g as i64)
+                .collect();
+            wrong_gate += self.out_order[g].len() - order.len();
+            inversions += (0..order.len())
+                .map(|i| order[i + 1..].iter().filter(|&&b| b < order[i]).count())
+                .sum::<usize>();
+        }
+        let undelivered = self.state.h * self.state.w - self.state.out_cnt;
+        self.ops.len() as i64
+            + 100 * inversions as i64
+            + 10_000 * wrong_gate as i64
+            + 1_000_000 * undelivered as i64
+    }
+
+    /* (x, y) に吊り上げられていないコンテナが置いてあるか */
+    fn ground_cont(&self, x: usize, y: usize) -> Option<i64> {
+        let cont = self.state.grid_cont[x][y][0];
+        (cont != -1 && !self.state.cont_suspended[x][y][0]).then_some(cont)
+    }
+
+    /* cont が今すぐ搬出してよいコンテナか (同じ搬出口でより小さいものが全て搬出済み) */
+    fn is_ready(&self, cont: i64) -> bool {
+        let g = cont as usize / self.state.h;
+        (g * self.state.h..cont as usize).all(|c| self.dispatched[c])
+    }
+
+    /* 他のクレーンの計画で使われていない、空いている仮置き場のうち from から運んで gate へ出すまでの距離が最小のマス */
+    fn buffer_cell(&self, from: (usize, usize), gate: (usize, usize)) -> Option<(usize, usize)> {
+        let (h, w) = (self.state.h, self.state.w);
+        (0..h)
+            .flat_map(|x| (1..w - 1).map(move |y| (x, y)))
+            .filter(|&(x, y)| {
+                self.state.grid_cont[x][y][0] == -1
+                    && self.state.grid_cont[x][y][1] == -1
+                    && self.state.grid_crane[x][y] == -1
+                    && self.tasks.iter().flatten().all(|t| t.to != (x, y))
+                    && self.loaded_cells.iter().flatten().all(|&p| p != (x, y))
+            })
+            .min_by_key(|&(x, y)| {
+                from.0.abs_diff(x) + from.1.abs_diff(y) + gate.0.abs_diff(x) + gate.1.abs_diff(y)
+            })
+    }
+
+    /*
+        運ぶコンテナの候補 (優先度, 吊り上げる位置, 吊り下げる位置) を列挙する関数
+        優先度は 今すぐ搬出できるコンテナ > 搬入口を空けるための仮置き > 順番を無視した搬出 (out_of_order の場合だけ)
+    */
+    #[allow(clippy::type_complexity)]
+    fn moves(&self, out_of_order: bool) -> Vec<(usize, (usize, usize), (usize, usize))> {
+        let (h, w) = (self.state.h, self.state.w);
+        let mut moves = vec![];
+        for x in 0..h {
+            for y in 0..w - 1 {
+                let Some(cont) = self.ground_cont(x, y) else {
+                    continue;
+                };
+                if self.tasks.iter().flatten().any(|t| t.from == (x, y)) {
+                    // 他のクレーンが運ぶ予定
+                    continue;
+                }
+                let gate = (cont as usize / h, w - 1);
+                if self.is_ready(cont) {
+                    moves.push((0, (x, y), gate));
+                } else if y == 0 {
+                    if let Some(buffer) = self.buffer_cell((x, y), gate) {
+                        moves.push((1, (x, y), buffer));
+                    }
+                }
+                if out_of_order {
+                    moves.push((2, (x, y), gate));
+                }
+            }
+        }
+        moves
+    }
+
+    /* 全て搬出するか MAX_TURN に達するまで、手の空いたクレーンに計画を立てて同時に動かす関数 */
+    pub fn run(&mut self) {
+        let (h, w) = (self.state.h, self.state.w);
+        let n = self.state.cranes.len();
+        for &c in self.to_bomb.iter() {
+            self.res.traj[c].clear();
+        }
+        let mut incoming = 0;
+        let mut stall = 0;
+        while self.state.out_cnt < h * w
+            && self.ops.len() < Self::MAX_TURN
+            && stall <= h * w
+            && !self.timed_out()
+        {
+            let arrived: usize = self.state.incoming_cont_idx.iter().sum();
+            if arrived != incoming {
+                incoming = arrived;
+                self.events += 1;
+            }
+
+            // 手の空いたクレーンにタスクを割り当てる
+            for c in 0..n {
+                let crane = &self.state.cranes[c];
+                if crane.exploded || self.to_bomb.contains(&c) || self.tasks[c].is_some() {
+                    continue;
+                }
+                if self.failed[c] != self.events && !self.assign(c) {
+                    self.failed[c] = self.events;
+                }
+            }
+
+            if self.tasks.iter().all(|t| t.is_none()) {
+                // どのクレーンも運べない場合は邪魔な小クレーンを爆破する
+                stall += 1;
+                let idle = (1..n)
+                    .rev()
+                    .find(|&c| self.state.cranes[c].bomb_ok() && !self.state.cranes[c].exploded);
+                if let Some(c) = idle {
+                    self.to_bomb.push(c);
+                    self.events += 1;
+                }
+            } else {
+                stall = 0;
+            }
+
+            let ops = (0..n)
+                .map(|c| {
+                    self.plans[c]
+                        .pop_front()
+                        .unwrap_or(Operation::Stop as usize)
+                })
+                .collect();
+            self.step(ops);
+
+            // 計画を終えたクレーンのタスクを完了にする
+            for c in 0..n {
+                if self.tasks[c].is_some() && self.plans[c].is_empty() {
+                    self.tasks[c] = None;
+                    self.loaded_cells[c].clear();
+                    self.events += 1;
+                }
+            }
+        }
+    }
+
+    /* クレーン c に、計画を立てられたタスクのうち最も良いものを割り当てる関数 */
+    fn assign(&mut self, c: usize) -> bool {
+        let crane = &self.state.cranes[c];
+        let start = (crane.x, crane.y);
+        let dist = |a: (usize, usize), b: (usize, usize)| a.0.abs_diff(b.0) + a.1.abs_diff(b.1);
+        let out_of_order = self.tasks.iter().all(|t| t.is_none());
+        let mut moves = if crane.suspended {
+            // 途中の出力から続ける場合などで荷物を持ったままのクレーンは、そのまま運ぶ
+            let (h, w) = (self.state.h, self.state.w);
+            let cont = self.state.grid_cont[start.0][start.1][crane.big as usize];
+            let gate = (cont as usize / h, w - 1);
+            let mut moves = vec![];
+            if self.is_ready(cont) {
+                moves.push((0, start, gate));
+            } else if let Some(buffer) = self.buffer_cell(start, gate) {
+                moves.push((1, start, buffer));
+            }
+            if out_of_order {
+                moves.push((2, start, gate));
+            }
+            moves
+        } else {
+            self.moves(out_of_order)
+        };
+        moves.sort_by_key(|&(priority, from, to)| (priority, dist(start, from) + dist(from, to)));
+        for &(_, from, to) in moves.iter().take(Self::TRY_TASKS) {
+            if let Some((ops, traj, loaded_cells)) = self.plan_task(c, start, from, to) {
+                self.plans[c] = ops.into();
+                self.res.traj[c] = traj;
+                self.tasks[c] = Some(Task { crane: c, from, to });
+                if !self.state.cranes[c].big {
+                    self.loaded_cells[c] = loaded_cells;
+                }
+                self.events += 1;
+                return true;
+            }
+        }
+        false
+    }
+
+    /*
+        クレーン c が start から from で吊り上げ、to で吊り下げ、搬出口以外のとどまれるマスへ退避する計画を立てる関数
+        (操作列, 各ターンの位置, 荷物を持って通るマス) を返す
+    */
+    #[allow(clippy::type_complexity)]
+    fn plan_task(
+        &self,
+        c: usize,
+        start: (usize, usize),
+        from: (usize, usize),
+        to: (usize, usize),
+    ) -> Option<(Vec<usize>, Vec<(usize, usize)>, Vec<(usize, usize)>)> {
+        let (h, w) = (self.state.h, self.state.w);
+        let occ = self.res.occupancy(c, h, w);
+        let mut ops = vec![];
+        let mut traj = vec![start];
+        let mut loaded_cells = vec![];
+        let follow = |path: Vec<usize>, ops: &mut Vec<usize>, traj: &mut Vec<(usize, usize)>| {
+            for dir in path {
+                let &(x, y) = traj.last().unwrap();
+                if dir < DIR_NUM {
+                    traj.push((
+                        (x as isize + DX[dir]) as usize,
+                        (y as isize + DY[dir]) as usize,
+                    ));
+                } else {
+                    traj.push((x, y));
+                }
+                ops.push(dir);
+            }
+        };
+        // 既に吊り上げている場合は運ぶだけ
+        let skip = self.state.cranes[c].suspended as usize;
+        for (loaded, goal, op) in [
+            (false, from, Operation::Suspend),
+            (true, to, Operation::Lower),
+        ]
+        .into_iter()
+        .skip(skip)
+        {
+            let (pos, k) = (*traj.last().unwrap(), ops.len());
+            let path = self.search(c, &occ, pos, k, loaded, Some(goal), |x, y, k| {
+                (x, y) == goal && !occ.conflict(k, goal, goal)
+            })?;
+            let len = traj.len();
+            follow(path, &mut ops, &mut traj);
+            if loaded {
+                loaded_cells.extend_from_slice(&traj[len..]);
+            }
+            ops.push(op as usize);
+            traj.push(goal);
+        }
+        // 搬出口にとどまると他のクレーンが搬出できないので退避する
+        let path = self.search(c, &occ, to, ops.len(), false, None, |x, y, k| {
+            y != w - 1 && occ.can_rest(k, (x, y))
+        })?;
+        follow(path, &mut ops, &mut traj);
+        Some((ops, traj, loaded_cells))
+    }
+
+    /*
+        予約表を避けて、k0 ターン後に start にいるクレーン c が goal を満たすマスに着くまでの操作列を時空間 A* で求める関数
+        target は A* の目安にする目的地 (None の場合は搬出口の列から出るまでの距離を使う)
+    */
+    #[allow(clippy::too_many_arguments)]
+    fn search(
+        &self,
+        c: usize,
+        occ: &Occupancy,
+        start: (usize, usize),
+        k0: usize,
+        loaded: bool,
+        target: Option<(usize, usize)>,
+        goal: impl Fn(usize, usize, usize) -> bool,
+    ) -> Option<Vec<usize>> {
+        let (h, w) = (self.state.h, self.state.w);
+        if target.is_some_and(|(x, y)| occ.last[x * w + y] == !0) {
+            // 他のクレーンがずっととどまるマスには着けない
+            return None;
+        }
+        // 荷物を持った小クレーンは、コンテナがある (置かれる予定の) マスとまだ搬入が残っている搬入口を通れない
+        let mut blocked = vec![vec![false; w]; h];
+        if loaded && !self.state.cranes[c].big {
+            for (x, row) in blocked.iter_mut().enumerate() {
+                for (y, b) in row.iter_mut().enumerate() {
+                    *b = self.ground_cont(x, y).is_some()
+                        || (y == 0
+                            && self.state.incoming_cont_idx[x] < w
+                            && Some((x, y)) != target);
+                }
+            }
+            for t in self.tasks.iter().flatten().filter(|t| t.crane != c) {
+                blocked[t.to.0][t.to.1] = true;
+            }
+        }
+        let heuristic = |x: usize, y: usize| match target {
+            Some((tx, ty)) => x.abs_diff(tx) + y.abs_diff(ty),
+            None => (y == w - 1) as usize,
+        };
+
+        // 状態 (k - k0, x, y) を 1 次元に並べる
+        let horizon = 4 * (h + w) + occ.cells.len();
+        let id = |k: usize, x: usize, y: usize| ((k - k0) * h + x) * w + y;
+        let mut parent = vec![!0; (horizon + 1) * h * w];
+        let mut heap = std::collections::BinaryHeap::new();
+        let start_id = id(k0, start.0, start.1);
+        parent[start_id] = start_id;
+        heap.push(std::cmp::Reverse((
+            heuristic(start.0, start.1),
+            k0,
+            start.0,
+            start.1,
+        )));
+        while let Some(std::cmp::Reverse((_, k, x, y))) = heap.pop() {
+            if goal(x, y, k) {
+                // 操作列を復元
+                let mut path = vec![];
+                let mut cur = id(k, x, y);
+                while cur != start_id {
+                    let prev = parent[cur];
+                    let (px, py) = ((prev / w) % h, prev % w);
+                    let (cx, cy) = ((cur / w) % h, cur % w);
+                    let dir = (0..DIR_NUM)
+                        .find(|&d| {
+                            px as isize + DX[d] == cx as isize && py as isize + DY[d] == cy as isize
+                        })
+                        .unwrap_or(Operation::Stop as usize);
+                    path.push(dir);
+                    cur = prev;
+                }
+                path.reverse();
+                return Some(path);
+            }
+            if k - k0 == horizon {
+                continue;
+            }
+            for dir in 0..=DIR_NUM {
+                // dir == DIR_NUM はその場で待機
+                let (nx, ny) = if dir < DIR_NUM {
+                    let nx = x as isize + DX[dir];
+                    let ny = y as isize + DY[dir];
+                    if out_field(nx, ny, h as isize, w as isize) {
+                        continue;
+                    }
+                    (nx as usize, ny as usize)
+                } else {
+                    (x, y)
+                };
+                let next = id(k + 1, nx, ny);
+                if parent[next] != !0 || (dir < DIR_NUM && blocked[nx][ny]) {
+                    continue;
+                }
+                if occ.conflict(k, (x, y), (nx, ny)) {
+                    continue;
+                }
+                parent[next] = id(k, x, y);
+                heap.push(std::cmp::Reverse((
+                    k + 1 + heuristic(nx, ny),
+                    k + 1,
+                    nx,
+                    ny,
+                )));
+            }
+        }
+        None
+    }
+
+    /*
+        クレーン c が start から goal へ行く最短距離と最初の移動方向を BFS で求める関数 (run_sequential 用)
+        他のクレーンのいるマスは通れず、荷物を持った小クレーンはコンテナのあるマスと、まだ搬入が残っている搬入口を通れない
+    */
+    pub fn route(
+        &self,
+        c: usize,
+        loaded: bool,
+        start: (usize, usize),
+        goal: (usize, usize),
+    ) -> Option<(usize, usize)> {
+        if start == goal {
+            return Some((0, !0));
+        }
+        let (h, w) = (self.state.h, self.state.w);
+        let big = self.state.cranes[c].big;
+        let passable = |x: usize, y: usize| {
+            let crane = self.state.grid_crane[x][y];
+            if crane != -1 && crane != c as isize {
+                return false;
+            }
+            if loaded && !big {
+                if self.ground_cont(x, y).is_some() {
+                    return false;
+                }
+                if y == 0 && self.state.incoming_cont_idx[x] < w && (x, y) != goal {
+                    return false;
+                }
+            }
+            true
+        };
+        if !passable(goal.0, goal.1) {
+            return None;
+        }
+
+        // goal から逆向きに BFS して、start の隣で距離が 1 小さいマスへ進む
+        let mut dist = vec![vec![!0; w]; h];
+        let mut queue = std::collections::VecDeque::new();
+        dist[goal.0][goal.1] = 0;
+        queue.push_back(goal);
+        while let Some((x, y)) = queue.pop_front() {
+            for dir in 0..DIR_NUM {
+                let nx = x as isize + DX[dir];
+                let ny = y as isize + DY[dir];
+                if out_field(nx, ny, h as isize, w as isize) {
+                    continue;
+                }
+                let (nx, ny) = (nx as usize, ny as usize);
+                if dist[nx][ny] != !0 {
+                    continue;
+                }
+                dist[nx][ny] = dist[x][y] + 1;
+                if (nx, ny) == start {
+                    // start から (x, y) へは dir の逆向きに進む
+                    return Some((dist[nx][ny], (dir + 2) % DIR_NUM));
+                }
+                if passable(nx, ny) {
+                    queue.push_back((nx, ny));
+                }
+            }
+        }
+        None
+    }
+
+    /* 次に実行するタスクを、優先度・クレーンの移動距離の順に選ぶ関数 (run_sequential 用) */
+    pub fn next_task(&self) -> Option<Task> {
+        let (h, w) = (self.state.h, self.state.w);
+        // ((優先度, 移動距離), タスク)
+        let mut cands: Vec<((usize, usize), Task)> = vec![];
+
+        // 途中で打ち切って荷物を持ったままのクレーンは、そのまま搬出口へ運ぶ
+        for (c, crane) in self.state.cranes.iter().enumerate() {
+            if crane.exploded || !crane.suspended {
+                continue;
+            }
+            let cont = self.state.grid_cont[crane.x][crane.y][crane.big as usize];
+            let g = cont as usize / h;
+            let from = (crane.x, crane.y);
+            let priority = if self.is_ready(cont) { 0 } else { 2 };
+            if let Some((d, _)) = self.route(c, true, from, (g, w - 1)) {
+                cands.push((
+                    (priority, d),
+                    Task {
+                        crane: c,
+                        from,
+                        to: (g, w - 1),
+                    },
+                ));
+            }
+        }
+
+        for (priority, from, to) in self.moves(true) {
+            if cands.iter().any(|&((p, _), _)| p < priority) {
+                continue;
+            }
+            for (c, crane) in self.state.cranes.iter().enumerate() {
+                if crane.exploded || crane.suspended {
+                    continue;
+                }
+                let Some((d1, _)) = self.route(c, false, (crane.x, crane.y), from) else {
+                    continue;
+                };
+                let Some((d2, _)) = self.route(c, true, from, to) else {
+                    continue;
+                };
+                cands.push(((priority, d1 + d2), Task { crane: c, from, to }));
+            }
+        }
+        cands
+            .into_iter()
+            .min_by_key(|&(key, _)| key)
+            .map(|(_, task)| task)
+    }
+
+    /* 1 台ずつタスクを選んで実行する関数 (他のクレーンは止めておく) */
+    pub fn run_sequential(&mut self) {
+        let (h, w) = (self.state.h, self.state.w);
+        while self.state.out_cnt < h * w && self.ops.len() < Self::MAX_TURN {
+            let turn = self.ops.len();
+            match self.next_task() {
+                Some(task) => self.execute(task),
+                None => {
+                    // どのクレーンも運べない場合は邪魔な小クレーンを爆破する
+                    let idle = (1..self.state.cranes.len()).rev().find(|&c| {
+                        self.state.cranes[c].bomb_ok() && !self.state.cranes[c].exploded
+                    });
+                    if let Some(c) = idle {
+                        self.to_bomb.push(c);
+                    }
+                }
+            }
+            if self.ops.len() == turn {
+                // 1 ターンも進まなかった場合は待つ
+                self.step_one(0, Operation::Stop as usize);
+            }
+        }
+    }
+
+    /* タスクを 1 ターンずつ実行する関数 (毎ターン経路を引き直し、進めなくなったら打ち切る) */
+    pub fn execute(&mut self, task: Task) {
+        let c = task.crane;
+        // 既に吊り上げている場合は運ぶだけ
+        let skip = self.state.cranes[c].suspended as usize;
+        for (loaded, goal) in [(false, task.from), (true, task.to)].into_iter().skip(skip) {
+            loop {
+                if self.ops.len() >= Self::MAX_TURN {
+                    return;
+                }
+                let crane = &self.state.cranes[c];
+                let pos = (crane.x, crane.y);
+                match self.route(c, loaded, pos, goal) {
+                    Some((0, _)) => break,
+                    Some((_, dir)) => self.step_one(c, dir),
+                    None => {
+                        // 運んでいる途中で塞がれた場合はその場に降ろす
+                        if loaded && self.ground_cont(pos.0, pos.1).is_none() {
+                            self.step_one(c, Operation::Lower as usize);
+                        }
+                        return;
+                    }
+                }
+            }
+            let op = if loaded {
+                Operation::Lower
+            } else {
+                Operation::Suspend
+            };
+            self.step_one(c, op as usize);
+        }
+    }
+
+    /* 各クレーンの出力行にする関数 (爆破後は何も出力しない) */
+    pub fn to_output(&self) -> Vec<String> {
+        let n = self.state.cranes.len();
+        let mut ans = vec![String::new(); n];
+        for ops in self.ops.iter() {
+            for (c, &op) in ops.iter().enumerate() {
+                if !ans[c].ends_with('B') {
+                    ans[c].push(OP[op]);
+                }
+            }
+        }
+        for a in ans.iter_mut().filter(|a| a.is_empty()) {
+            a.push('.');
+        }
+        ans
+    }
+}
+
+/* 初期状態のプランナー (使わないクレーンは最初のターンに爆破) */
+fn initial_planner(input: &Input, config: &SolverConfig) -> Planner {
+    let mut planner = Planner::new(initial_planner_state(input, config));
+    if !config.joint_move {
+        planner.to_bomb = (0..input.n)
+            .filter(|c| !config.crane_perm.contains(c))
+            .collect();
+    }
+    planner
+}
+
+/* プランナーが使う初期盤面 */
+fn initial_planner_state(input: &Input, config: &SolverConfig) -> Terminal {
+    let mut terminal = Terminal::new(input, config, Potential::new(input));
+    terminal.prepare_cont();
+    terminal.score = terminal.evaluate();
+    terminal.hash = terminal.compute_hash();
+    terminal
+}
+
+/*
+    planner の続きを協調 A* と 1 台ずつ動かす方法で計画し、得点が良い方を返す関数
+    協調 A* が planner.deadline で打ち切られた場合は、その続きを 1 台ずつ動かす方法で計画する
+*/
+fn plan_from(planner: Planner) -> Planner {
+    let mut cooperative = planner;
+    let mut sequential = cooperative.clone();
+    cooperative.run();
+    if cooperative.timed_out() {
+        cooperative.run_sequential();
+    }
+    sequential.run_sequential();
+    eprintln!(
+        "planner score: {} (cooperative, {} turns), {} (sequential, {} turns)",
+        cooperative.score(),
+        cooperative.ops.len(),
+        sequential.score(),
+        sequential.ops.len()
+    );
+    if cooperative.score() <= sequential.score() {
+        cooperative
+    } else {
+        sequential
+    }
+}
+
+/* プランナーだけで解く関数 (協調 A* と 1 台ずつ動かす方法の得点が良い方を使い、使わないクレーンは最初のターンに爆破) */
+pub fn plan(input: &Input, config: &SolverConfig) -> Vec<String> {
+    plan_from(initial_planner(input, config)).to_output()
+}
+
+/*
+    ビームサーチの途中の出力 partials を完成させる関数
+    ジャッジと同じ式の得点が最も良い途中の出力から続きをプランナーで計画する (詰まったクレーンはプランナーが爆破する)
+    最初からプランナーで解いた解や、続きを計画しない途中の出力のままの方が良ければそれを使う
+    プランナーは deadline で打ち切り、それまでに得られた候補から選ぶ
+*/
+pub fn complete(
+    input: &Input,
+    config: &SolverConfig,
+    partials: &[Vec<String>],
+    deadline: std::time::Instant,
+) -> Vec<String> {
+    let mut cands = vec![];
+    if let Some(mut partial) = partials
+        .iter()
+        .map(|ans| Planner::replay(initial_planner_state(input, config), ans))
+        .min_by_key(|planner| planner.score())
+    {
+        // 最初から解く分を残すため、途中の出力の続きの協調 A* には残り時間の半分を使う
+        let now = std::time::Instant::now();
+        partial.deadline = Some(now + deadline.saturating_duration_since(now) / 2);
+        eprintln!(
+            "partial score: {} ({} turns)",
+            partial.score(),
+            partial.ops.len()
+        );
+        cands.push(plan_from(partial.clone()));
+        cands.push(partial);
+    }
+    // 途中の出力の続きで時間を使い切った場合は、最初からは計画し直さない
+    if cands.is_empty() || std::time::Instant::now() < deadline {
+        let mut initial = initial_planner(input, config);
+        initial.deadline = Some(deadline);
+        cands.push(plan_from(initial));
+    }
+    cands
+        .into_iter()
+        .min_by_key(|planner| planner.score())
+        .unwrap()
+        .to_output()
+}
@@ -0,0 +1,645 @@
+// ソルバーの Terminal とジャッジの tools::State を、ランダムな合法手順で突き合わせるテスト
+// 食い違った場合は手順を縮めて、最小の反例を tools の vis に渡せる形で表示する
+use ahc033::*;
+use rand::prelude::*;
+
+const CASES: u64 = 300; // 試す入力の数
+const TURNS: usize = 100; // 1 つの手順のターン数
+const RETRY: usize = 20; // 同時操作で衝突しない組み合わせを引き直す回数
+const EXHAUSTIVE_CASES: u64 = 100; // 1 ターン分の操作を全て試す盤面の数
+const BEAM_CASES: u64 = 12; // 短いビームサーチを回す入力の数
+const BEAM_LAYERS: usize = 30; // そのビームサーチの層数
+const BEAM_WIDTH: usize = 10; // そのビームサーチの幅
+
+/* 1 つの検査の設定 */
+struct Case {
+    input: Input,
+    judge_input: tools::Input,
+    config: SolverConfig,
+}
+
+/* 手順を再生した結果 */
+#[derive(Debug, PartialEq)]
+enum Outcome {
+    Agree,
+    Illegal,                 // ソルバーが不正とする手順 (縮める途中の候補としては使えない)
+    Mismatch(usize, String), // (食い違ったターン, 内容)
+}
+
+impl Case {
+    /* judge_input の入力をソルバーの設定 config (resolve 済み) で動かす */
+    fn new(judge_input: tools::Input, config: SolverConfig) -> Self {
+        let nums: Vec<i64> = judge_input
+            .to_string()
+            .split_whitespace()
+            .map(|v| v.parse().unwrap())
+            .collect();
+        let n = nums[0] as usize;
+        let input = Input {
+            n,
+            a: nums[1..].chunks(n).map(|row| row.to_vec()).collect(),
+        };
+        Self {
+            input,
+            judge_input,
+            config,
+        }
+    }
+
+    /* seed から作ったランダムな入力 (1 台ずつ動かす場合はクレーンを動かす順番もランダム) */
+    fn random(seed: u64, n: usize, joint_move: bool, penalty: bool) -> Self {
+        let mut config = SolverConfig {
+            joint_move,
+            penalty,
+            evaluator: if penalty {
+                EvaluatorKind::ScoreAware
+            } else {
+                EvaluatorKind::Potential
+            },
+            ..Default::default()
+        };
+        config.resolve(n).unwrap();
+        if !joint_move {
+            config
+                .crane_perm
+                .shuffle(&mut StdRng::seed_from_u64(seed + CASES));
+        }
+        Self::new(tools::gen(seed, n, tools::GenMode::Uniform), config)
+    }
+
+    /* 固定の入力 text を、同時操作か crane_perm の順に 1 台ずつ動かす */
+    fn fixed(text: &str, joint_move: bool, crane_perm: &[usize]) -> Self {
+        let judge_input = tools::parse_input(text);
+        let mut config = SolverConfig {
+            joint_move,
+            crane_perm: crane_perm.to_vec(),
+            using_crane: crane_perm.len(),
+            ..Default::default()
+        };
+        let n = text.split_whitespace().next().unwrap().parse().unwrap();
+        config.resolve(n).unwrap();
+        Self::new(judge_input, config)
+    }
+
+    /* 手順をジャッジだけで再生した後の盤面 */
+    fn judge(&self, seq: &[Vec<char>]) -> tools::Snapshot {
+        let mut judge = tools::State::new(&self.judge_input);
+        for mv in seq.iter() {
+            judge.apply(mv).unwrap();
+        }
+        judge.snapshot()
+    }
+
+    fn terminal(&self) -> Terminal {
+        let mut state = Terminal::new(&self.input, &self.config, Potential::new(&self.input));
+        state.prepare_cont();
+        state.score = state.evaluate();
+        state.hash = state.compute_hash();
+        state
+    }
+
+    /* ソルバーが合法とする操作から、ランダムな手順を作る (吊り上げ・吊り下げを選びやすくする) */
+    fn generate(&self, rng: &mut StdRng) -> Vec<Vec<char>> {
+        let n = self.input.n;
+        let mut state = self.terminal();
+        let pick = |ops: &[usize], rng: &mut StdRng| -> usize {
+            *ops.choose_weighted(rng, |&op| match op {
+                0 => 6.0,
+                4 | 5 => 8.0,
+                7 => 0.05,
+                _ => 4.0,
+            })
+            .unwrap()
+        };
+        let mut seq = vec![];
+        for _ in 0..TURNS {
+            let mut mv = vec!['.'; n];
+            if self.config.joint_move {
+                let legal: Vec<Vec<usize>> = (0..n)
+                    .map(|c| {
+                        (0..OP_NUM)
+                            .filter(|&op| state.joint_action_ok(c, op))
+                            .collect()
+                    })
+                    .collect();
+                for _ in 0..RETRY {
+                    let ops: Vec<usize> = legal.iter().map(|ops| pick(ops, rng)).collect();
+                    if !collides(&state, &ops) {
+                        for (c, &op) in ops.iter().enumerate() {
+                            mv[c] = OP[op];
+                        }
+                        break;
+                    }
+                }
+                let ops: Vec<usize> = mv.iter().map(|&ch| op_index(ch)).collect();
+                state.apply_joint(&ops);
+            } else {
+                for _ in 0..self.config.crane_perm.len() {
+                    let c = state.moving_crane();
+                    let legal: Vec<usize> =
+                        (0..OP_NUM).filter(|&op| step_ok(&state, c, op)).collect();
+                    let op = pick(&legal, rng);
+                    mv[c] = OP[op];
+                    state.apply(&Node {
+                        op,
+                        ..Default::default()
+                    });
+                }
+            }
+            seq.push(mv);
+        }
+        seq
+    }
+
+    /* 手順をソルバーとジャッジで 1 ターンずつ再生し、毎ターン盤面を比べる */
+    fn check(&self, seq: &[Vec<char>]) -> Outcome {
+        self.run(seq).0
+    }
+
+    /* check と同じく再生し、結果と再生し終えた (または食い違った) 時点のソルバー・ジャッジの状態を返す */
+    fn run(&self, seq: &[Vec<char>]) -> (Outcome, Terminal, tools::State) {
+        let mut state = self.terminal();
+        let mut judge = tools::State::new(&self.judge_input);
+        for (t, mv) in seq.iter().enumerate() {
+            let ops: Vec<usize> = mv.iter().map(|&ch| op_index(ch)).collect();
+            if self.config.joint_move {
+                let legal = (0..ops.len()).all(|c| state.joint_action_ok(c, ops[c]));
+                if !legal || collides(&state, &ops) {
+                    return (Outcome::Illegal, state, judge);
+                }
+                state.apply_joint(&ops);
+            } else {
+                for _ in 0..self.config.crane_perm.len() {
+                    let c = state.moving_crane();
+                    if !step_ok(&state, c, ops[c]) {
+                        return (Outcome::Illegal, state, judge);
+                    }
+                    state.apply(&Node {
+                        op: ops[c],
+                        ..Default::default()
+                    });
+                }
+            }
+            if let Err(err) = judge.apply(mv) {
+                let err = format!("the judge rejected the turn: {}", err);
+                return (Outcome::Mismatch(t, err), state, judge);
+            }
+            if let Some(diff) = compare(&state, &judge.snapshot()) {
+                return (Outcome::Mismatch(t, diff), state, judge);
+            }
+        }
+        (Outcome::Agree, state, judge)
+    }
+
+    /* 反例を、入力と出力 (tools の vis にそのまま渡せる形) で表示する */
+    fn report(&self, seq: &[Vec<char>], outcome: &Outcome) -> String {
+        let rows: Vec<String> = (0..self.input.n)
+            .map(|c| seq.iter().map(|mv| mv[c]).collect())
+            .collect();
+        format!(
+            "{:?} (joint_move = {}, penalty = {}, crane_perm = {:?})\ninput:\n{}output:\n{}",
+            outcome,
+            self.config.joint_move,
+            self.config.penalty,
+            self.config.crane_perm,
+            self.judge_input,
+            rows.join("\n")
+        )
+    }
+}
+
+/* 出力の形 (クレーンごとの行) の手順を、ターンごとの全クレーンの操作に直す */
+fn turns(rows: &[&str]) -> Vec<Vec<char>> {
+    let t = rows.iter().map(|row| row.len()).max().unwrap();
+    (0..t)
+        .map(|k| {
+            rows.iter()
+                .map(|row| row.chars().nth(k).unwrap_or('.'))
+                .collect()
+        })
+        .collect()
+}
+
+/* 1 台ずつ動かす場合に、クレーン c が op をしてよいか (爆破済みのクレーンは停止だけ) */
+fn step_ok(state: &Terminal, c: usize, op: usize) -> bool {
+    let crane = &state.cranes[c];
+    if crane.exploded {
+        return op == Operation::Stop as usize;
+    }
+    crane.action_ok(op, &state.grid_crane, &state.grid_cont, &state.out_cont_idx)
+}
+
+fn op_index(ch: char) -> usize {
+    OP.iter().position(|&op| op == ch).unwrap()
+}
+
+/* 同時操作で、移動先が同じマスになるか、すれ違うクレーンがあるか (爆破済み・爆破するクレーンは除く) */
+fn collides(state: &Terminal, ops: &[usize]) -> bool {
+    let dest: Vec<Option<(usize, usize)>> = ops
+        .iter()
+        .enumerate()
+        .map(|(c, &op)| {
+            let crane = &state.cranes[c];
+            if crane.exploded || op == Operation::Bomb as usize {
+                None
+            } else if op < DIR_NUM {
+                Some((
+                    (crane.x as isize + DX[op]) as usize,
+                    (crane.y as isize + DY[op]) as usize,
+                ))
+            } else {
+                Some((crane.x, crane.y))
+            }
+        })
+        .collect();
+    let pos = |c: usize| Some((state.cranes[c].x, state.cranes[c].y));
+    (0..ops.len()).any(|i| {
+        dest[i].is_some()
+            && (0..i).any(|j| dest[j] == dest[i] || (dest[j] == pos(i) && dest[i] == pos(j)))
+    })
+}
+
+/* ソルバーの盤面とジャッジの盤面の最初の違い (同じなら None) */
+fn compare(state: &Terminal, judge: &tools::Snapshot) -> Option<String> {
+    let (h, w) = (state.h, state.w);
+    for x in 0..h {
+        for y in 0..w {
+            let cont = if state.cont_on_ground(x, y) {
+                state.grid_cont[x][y][0] as i32
+            } else {
+                -1
+            };
+            if cont != judge.board[x][y] {
+                return Some(format!(
+                    "container on ({}, {}): solver {}, judge {}",
+                    x, y, cont, judge.board[x][y]
+                ));
+            }
+        }
+    }
+    for (c, crane) in state.cranes.iter().enumerate() {
+        let ours = if crane.exploded {
+            (!0, !0, -1)
+        } else if crane.suspended {
+            let hold = state.grid_cont[crane.x][crane.y][crane.big as usize] as i32;
+            (crane.x, crane.y, hold)
+        } else {
+            (crane.x, crane.y, -1)
+        };
+        if ours != judge.cranes[c] {
+            return Some(format!(
+                "crane {} (x, y, hold): solver {:?}, judge {:?}",
+                c, ours, judge.cranes[c]
+            ));
+        }
+    }
+    let dispatched: Vec<i32> = (0..h * w)
+        .filter(|&cont| state.dispatched >> cont & 1 == 1)
+        .map(|cont| cont as i32)
+        .collect();
+    if dispatched != judge.dispatched || state.out_cnt != dispatched.len() {
+        return Some(format!(
+            "dispatched: solver {:?} ({} in out_cnt), judge {:?}",
+            dispatched, state.out_cnt, judge.dispatched
+        ));
+    }
+    if state.wrong_gate != judge.wrong_gate.len() {
+        return Some(format!(
+            "wrong gate: solver {}, judge {:?}",
+            state.wrong_gate, judge.wrong_gate
+        ));
+    }
+    None
+}
+
+/*
+    fails を満たしたまま手順を縮める (最小の反例にする)
+    ターンをまとめて消すのを半分ずつの長さで試し、その後 1 つずつ操作を停止に置き換える、を変わらなくなるまで繰り返す
+*/
+fn shrink(mut seq: Vec<Vec<char>>, fails: impl Fn(&[Vec<char>]) -> bool) -> Vec<Vec<char>> {
+    loop {
+        let mut shrunk = false;
+        let mut chunk = seq.len() / 2;
+        while chunk >= 1 {
+            let mut i = 0;
+            while i + chunk <= seq.len() {
+                let mut cand = seq.clone();
+                cand.drain(i..i + chunk);
+                if fails(&cand) {
+                    seq = cand;
+                    shrunk = true;
+                } else {
+                    i += 1;
+                }
+            }
+            chunk /= 2;
+        }
+        for t in 0..seq.len() {
+            for c in 0..seq[t].len() {
+                if seq[t][c] == '.' {
+                    continue;
+                }
+                let mut cand = seq.clone();
+                cand[t][c] = '.';
+                if fails(&cand) {
+                    seq = cand;
+                    shrunk = true;
+                }
+            }
+        }
+        if !shrunk {
+            return seq;
+        }
+    }
+}
+
+/* N = 2..=5 と penalty の有無を変えながら、ランダムな手順でソルバーとジャッジが一致するかを調べる */
+fn agree_with_judge(joint_move: bool) {
+    for seed in 0..CASES {
+        let n = MIN_N + seed as usize % 4;
+        let case = Case::random(seed, n, joint_move, seed % 2 == 1);
+        let mut rng = StdRng::seed_from_u64(seed);
+        let seq = case.generate(&mut rng);
+        let outcome = case.check(&seq);
+        if outcome == Outcome::Agree {
+            continue;
+        }
+        let minimal = shrink(seq, |seq| matches!(case.check(seq), Outcome::Mismatch(..)));
+        panic!(
+            "seed {}: {}",
+            seed,
+            case.report(&minimal, &case.check(&minimal))
+        );
+    }
+}
+
+#[test]
+fn joint_moves_agree_with_judge() {
+    agree_with_judge(true);
+}
+
+#[test]
+fn step_moves_agree_with_judge() {
+    agree_with_judge(false);
+}
+
+#[test]
+fn generated_sequences_are_legal() {
+    // 生成した手順がソルバー自身に不正とされると、比べずに通ってしまう
+    for joint_move in [false, true] {
+        for seed in 0..20 {
+            let case = Case::random(seed, 3, joint_move, false);
+            let seq = case.generate(&mut StdRng::seed_from_u64(seed));
+            assert_ne!(case.check(&seq), Outcome::Illegal, "seed {}", seed);
+        }
+    }
+}
+
+#[test]
+fn shrink_finds_minimal_counterexample() {
+    // クレーン 1 が吊り下げるターンがあると失敗する、という性質なら 1 ターン 1 操作まで縮む
+    let mut rng = StdRng::seed_from_u64(0);
+    let mut seq: Vec<Vec<char>> = (0..50)
+        .map(|_| (0..3).map(|_| *OP.choose(&mut rng).unwrap()).collect())
+        .collect();
+    seq[37][1] = 'Q';
+    let minimal = shrink(seq, |seq| seq.iter().any(|mv| mv[1] == 'Q'));
+    assert_eq!(minimal, vec![vec!['.', 'Q', '.']]);
+}
+
+// 搬入口の扱いが紛らわしい場面
+// ジャッジはターンの終わりに、地面にコンテナがなく、コンテナを吊り上げたクレーンもいない搬入口にだけ搬入する
+const GATE_INPUT: &str = "2\n0 1\n2 3\n";
+
+#[test]
+fn no_carry_in_under_loaded_crane_entering_gate() {
+    // 小クレーン 1 が荷物を持って (1, 0) を出たターンに、大クレーン 0 が荷物を持って (1, 0) に入る
+    // 1 台ずつ動かす場合も、小クレーン 1 が動いた直後には搬入しない
+    for (joint_move, perm) in [(true, [0, 1]), (false, [1, 0])] {
+        let case = Case::fixed(GATE_INPUT, joint_move, &perm);
+        let seq = turns(&["PD", "PR"]);
+        assert_eq!(
+            case.check(&seq),
+            Outcome::Agree,
+            "joint_move = {}",
+            joint_move
+        );
+        let board = case.judge(&seq).board;
+        assert_eq!((board[0][0], board[1][0]), (1, -1));
+
+        // 大クレーンが去ると搬入される
+        let seq = turns(&["PDU", "PRQ"]);
+        assert_eq!(
+            case.check(&seq),
+            Outcome::Agree,
+            "joint_move = {}",
+            joint_move
+        );
+        let snapshot = case.judge(&seq);
+        assert_eq!(
+            (snapshot.board[1][0], snapshot.dispatched.clone()),
+            (3, vec![2])
+        );
+    }
+}
+
+#[test]
+fn carry_in_under_empty_crane() {
+    // 大クレーン 0 が荷物を持って (0, 0) を出たターンに、何も持たない小クレーン 1 が (0, 0) に入ると、その下に搬入される
+    for (joint_move, perm) in [(true, [0, 1]), (false, [0, 1])] {
+        let case = Case::fixed(GATE_INPUT, joint_move, &perm);
+        let seq = turns(&["PR", ".U"]);
+        assert_eq!(
+            case.check(&seq),
+            Outcome::Agree,
+            "joint_move = {}",
+            joint_move
+        );
+        let snapshot = case.judge(&seq);
+        assert_eq!(snapshot.board[0][0], 1);
+        assert_eq!(snapshot.cranes[1], (0, 0, -1));
+    }
+}
+
+#[test]
+fn no_carry_in_while_holding_on_gate() {
+    // 搬入口で吊り上げている間は搬入されず、同じ場所に降ろすと元に戻る (大クレーン・小クレーンとも)
+    for joint_move in [true, false] {
+        let case = Case::fixed(GATE_INPUT, joint_move, &[0, 1]);
+        let seq = turns(&["P", "P"]);
+        assert_eq!(
+            case.check(&seq),
+            Outcome::Agree,
+            "joint_move = {}",
+            joint_move
+        );
+        let snapshot = case.judge(&seq);
+        assert_eq!((snapshot.board[0][0], snapshot.board[1][0]), (-1, -1));
+        assert_eq!((snapshot.cranes[0].2, snapshot.cranes[1].2), (0, 2));
+
+        let seq = turns(&["PQ", "PQ"]);
+        assert_eq!(
+            case.check(&seq),
+            Outcome::Agree,
+            "joint_move = {}",
+            joint_move
+        );
+        let board = case.judge(&seq).board;
+        assert_eq!((board[0][0], board[1][0]), (0, 2));
+    }
+}
+
+/*
+    1 台ずつ動かす場合に、ソルバーが合法とする 1 ターン分の操作の組み合わせを全て試し、ジャッジが拒否しないかを調べる
+    action_ok は同じターンに先に動いたクレーンの移動後の grid_crane を見るので、2 台がすれ違うにはお互いが先に動く必要があり起こらない
+    (同じマスに入る・すれ違う・コンテナのあるマスに荷物を持って入る、のどれもジャッジと食い違わないことをここで確かめる)
+*/
+#[test]
+fn every_legal_step_turn_is_accepted_by_judge() {
+    for seed in 0..EXHAUSTIVE_CASES {
+        let n = MIN_N + seed as usize % 3;
+        let case = Case::random(seed, n, false, seed % 2 == 1);
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut prefix = case.generate(&mut rng);
+        prefix.truncate(rng.gen_range(0..TURNS));
+        let (outcome, mut state, judge) = case.run(&prefix);
+        assert_eq!(outcome, Outcome::Agree, "seed {}", seed);
+        let mut mv = vec!['.'; n];
+        try_every_turn(&case, &mut state, &judge, &prefix, &mut mv, 0);
+    }
+}
+
+/* k 番目に動くクレーン以降の操作を全て試し、ターンを終えるたびにジャッジと比べる */
+fn try_every_turn(
+    case: &Case,
+    state: &mut Terminal,
+    judge: &tools::State,
+    prefix: &[Vec<char>],
+    mv: &mut Vec<char>,
+    k: usize,
+) {
+    if k == case.config.crane_perm.len() {
+        let mut judge = judge.clone();
+        let outcome = match judge.apply(mv) {
+            Err(err) => Outcome::Mismatch(
+                prefix.len(),
+                format!("the judge rejected the turn: {}", err),
+            ),
+            Ok(()) => match compare(state, &judge.snapshot()) {
+                Some(diff) => Outcome::Mismatch(prefix.len(), diff),
+                None => return,
+            },
+        };
+        let mut seq = prefix.to_vec();
+        seq.push(mv.clone());
+        panic!("{}", case.report(&seq, &outcome));
+    }
+    let c = state.moving_crane();
+    let legal: Vec<usize> = (0..OP_NUM).filter(|&op| step_ok(state, c, op)).collect();
+    for op in legal {
+        mv[c] = OP[op];
+        state.apply(&Node {
+            op,
+            ..Default::default()
+        });
+        try_every_turn(case, state, judge, prefix, mv, k + 1);
+        state.revert();
+    }
+    mv[c] = '.';
+}
+
+#[test]
+fn joint_legal_agrees_with_judge() {
+    // 局所探索が使う joint_legal は、1 ターン分の全ての操作の組み合わせでジャッジと同じ判定をする
+    for seed in 0..EXHAUSTIVE_CASES {
+        let n = MIN_N + seed as usize % 3;
+        let case = Case::random(seed, n, true, false);
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut prefix = case.generate(&mut rng);
+        prefix.truncate(rng.gen_range(0..TURNS));
+        let (outcome, state, judge) = case.run(&prefix);
+        assert_eq!(outcome, Outcome::Agree, "seed {}", seed);
+        for code in 0..OP_NUM.pow(n as u32) {
+            let ops = decode_joint(code, n);
+            let mv: Vec<char> = ops.iter().map(|&op| OP[op]).collect();
+            let accepted = judge.clone().apply(&mv).is_ok();
+            assert_eq!(
+                state.joint_legal(&ops),
+                accepted,
+                "seed {}: {:?} after {:?}",
+                seed,
+                mv,
+                prefix
+            );
+        }
+    }
+}
+
+#[test]
+fn beam_search_keeps_hash_and_reverts() {
+    // 短いビームサーチを回し、各層の葉まで初期盤面から apply し直して、差分更新した hash が一から計算したものと一致し、
+    // revert すると apply 前の盤面に戻ることを確かめる (候補を列挙するたびに check_revert も走る)
+    for seed in 0..BEAM_CASES {
+        let n = MIN_N + seed as usize % 3;
+        let case = Case::random(seed, n, seed % 2 == 1, false);
+        let initial = case.terminal();
+        let root = Node {
+            op: !0,
+            parent: !0,
+            child: !0,
+            prev: !0,
+            next: !0,
+        };
+        let mut solver = BeamSearch::new(initial.clone(), root, &case.config);
+        for layer in 0..BEAM_LAYERS {
+            let mut cands = vec![];
+            solver.enum_cands(&mut cands);
+            cands.sort_by_key(|cand| cand.eval_score);
+            if cands[0].done {
+                break;
+            }
+            solver.update(cands.into_iter(), BEAM_WIDTH);
+
+            for &leaf in solver.leaf.iter() {
+                let mut path = vec![];
+                let mut idx = leaf;
+                while solver.nodes[idx].op != !0 {
+                    path.push(idx);
+                    idx = solver.nodes[idx].parent;
+                }
+                let mut state = initial.clone();
+                for &idx in path.iter().rev() {
+                    let before = without_undo(&state);
+                    let depth = state.undo.frames.len();
+                    state.apply(&solver.nodes[idx]);
+                    assert_eq!(
+                        state.hash,
+                        state.compute_hash(),
+                        "seed {}, layer {}: incremental hash differs",
+                        seed,
+                        layer
+                    );
+                    let after = without_undo(&state);
+                    state.revert();
+                    assert!(
+                        without_undo(&state) == before && state.undo.frames.len() == depth,
+                        "seed {}, layer {}: revert did not restore the state (op {})",
+                        seed,
+                        layer,
+                        solver.nodes[idx].op
+                    );
+                    state.apply(&solver.nodes[idx]);
+                    assert!(without_undo(&state) == after);
+                }
+            }
+        }
+    }
+}
+
+/* undo log を除いた盤面 (apply -> revert で比べるのはこちら) */
+fn without_undo(state: &Terminal) -> Terminal {
+    Terminal {
+        undo: UndoLog::default(),
+        ..state.clone()
+    }
+}
@@ -0,0 +1,272 @@
+#![allow(non_snake_case)]
+
+use clap::Parser;
+use std::{
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+use tools::*;
+
+#[derive(Parser, Debug)]
+struct Cli {
+    /// Path to the solver binary
+    #[clap(short, long, default_value = "../target/release/AHC033")]
+    solver: PathBuf,
+    /// Path to input directory
+    #[clap(short = 'd', long = "dir", default_value = "in")]
+    dir: PathBuf,
+    /// Path to output directory (stdout and stderr of the solver are saved here)
+    #[clap(short, long, default_value = "out")]
+    out: PathBuf,
+    /// Path to the best scores (one line per case)
+    #[clap(short, long, default_value = "scores_best")]
+    best: PathBuf,
+    /// Path to write the scores of this run
+    #[clap(short, long, default_value = "scores_now")]
+    now: PathBuf,
+    /// Number of cases run in parallel (default: number of CPUs)
+    #[clap(short, long)]
+    jobs: Option<usize>,
+    /// Per-case timeout in seconds
+    #[clap(short, long, default_value_t = 10.0)]
+    timeout: f64,
+    /// Overwrite the best scores with the improved ones
+    #[clap(short, long)]
+    update_best: bool,
+    /// Arguments passed to the solver
+    #[clap(last = true)]
+    args: Vec<String>,
+}
+
+#[derive(Clone, Debug)]
+enum Outcome {
+    Score(ScoreBreakdown),
+    /// The solver finished but the output was rejected by the judge
+    Invalid(String),
+    /// The solver crashed or exceeded the timeout
+    Failed(String),
+}
+
+#[derive(Clone, Debug)]
+struct CaseResult {
+    name: String,
+    outcome: Outcome,
+    elapsed: f64,
+}
+
+fn main() {
+    let cli = Cli::parse();
+    if !cli.solver.exists() {
+        eprintln!("no such solver: {} (build it with `cargo build -r`)", cli.solver.display());
+        std::process::exit(1)
+    }
+    if !cli.out.exists() {
+        std::fs::create_dir(&cli.out).unwrap();
+    }
+    let mut cases: Vec<PathBuf> = std::fs::read_dir(&cli.dir)
+        .unwrap_or_else(|_| {
+            eprintln!("no such directory: {}", cli.dir.display());
+            std::process::exit(1)
+        })
+        .map(|e| e.unwrap().path())
+        .filter(|p| p.extension().is_some_and(|e| e == "txt"))
+        .collect();
+    cases.sort();
+    if cases.is_empty() {
+        eprintln!("no input files in {}", cli.dir.display());
+        std::process::exit(1)
+    }
+
+    // 全ケースをスレッドで分担して実行する
+    let jobs = cli
+        .jobs
+        .unwrap_or_else(|| std::thread::available_parallelism().map_or(1, |n| n.get()))
+        .max(1);
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new(vec![None; cases.len()]);
+    std::thread::scope(|s| {
+        for _ in 0..jobs {
+            s.spawn(|| loop {
+                let i = next.fetch_add(1, Ordering::SeqCst);
+                if i >= cases.len() {
+                    break;
+                }
+                let result = run_case(&cli, &cases[i]);
+                results.lock().unwrap()[i] = Some(result);
+            });
+        }
+    });
+    let results: Vec<CaseResult> = results.into_inner().unwrap().into_iter().map(|r| r.unwrap()).collect();
+
+    let mut best = read_best(&cli.best);
+    report(&results, &best);
+
+    // 今回のスコアを保存 (不正・失敗は 0)
+    let now = results
+        .iter()
+        .map(|r| match r.outcome {
+            Outcome::Score(ref details) => format!("{}\n", details.score),
+            _ => "0\n".to_owned(),
+        })
+        .collect::<String>();
+    std::fs::write(&cli.now, now).unwrap();
+
+    if cli.update_best {
+        let mut updated = 0;
+        best.resize(best.len().max(results.len()), None);
+        for (i, r) in results.iter().enumerate() {
+            if let Outcome::Score(ref details) = r.outcome {
+                let score = details.score;
+                if best[i].is_none_or(|b| score < b) {
+                    best[i] = Some(score);
+                    updated += 1;
+                }
+            }
+        }
+        let text = best
+            .iter()
+            .map(|b| b.map_or("\n".to_owned(), |b| format!("{}\n", b)))
+            .collect::<String>();
+        std::fs::write(&cli.best, text).unwrap();
+        println!("updated {} best scores in {}", updated, cli.best.display());
+    }
+}
+
+/// Run the solver on one input and score its output.
+fn run_case(cli: &Cli, path: &Path) -> CaseResult {
+    let name = path.file_stem().unwrap().to_string_lossy().to_string();
+    let out_path = cli.out.join(format!("{}.txt", name));
+    let err_path = cli.out.join(format!("{}.err", name));
+
+    let start = Instant::now();
+    let status = (|| -> Result<(), String> {
+        let mut child = Command::new(&cli.solver)
+            .args(&cli.args)
+            .stdin(Stdio::from(std::fs::File::open(path).map_err(|e| e.to_string())?))
+            .stdout(Stdio::from(std::fs::File::create(&out_path).map_err(|e| e.to_string())?))
+            .stderr(Stdio::from(std::fs::File::create(&err_path).map_err(|e| e.to_string())?))
+            .spawn()
+            .map_err(|e| e.to_string())?;
+        let timeout = Duration::from_secs_f64(cli.timeout);
+        loop {
+            if let Some(status) = child.try_wait().map_err(|e| e.to_string())? {
+                if status.success() {
+                    return Ok(());
+                }
+                return Err(format!("exited with {} (see {})", status, err_path.display()));
+            }
+            if start.elapsed() > timeout {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(format!("timeout ({:.1}s)", cli.timeout));
+            }
+            std::thread::sleep(Duration::from_millis(5));
+        }
+    })();
+    let elapsed = start.elapsed().as_secs_f64();
+
+    let outcome = match status {
+        Err(err) => Outcome::Failed(err),
+        Ok(()) => {
+            let input = parse_input(&std::fs::read_to_string(path).unwrap());
+            let output = std::fs::read_to_string(&out_path).unwrap();
+            match parse_output(&input, &output) {
+                Err(err) => Outcome::Invalid(err),
+                Ok(out) => {
+                    let t = out.out.iter().map(|s| s.len()).max().unwrap();
+                    let (details, err, _) = compute_score_details(&input, &out, t);
+                    if !err.is_empty() {
+                        Outcome::Invalid(err)
+                    } else {
+                        Outcome::Score(details)
+                    }
+                }
+            }
+        }
+    };
+    CaseResult { name, outcome, elapsed }
+}
+
+/// Read the best scores. Empty or unparsable lines mean no best score.
+fn read_best(path: &Path) -> Vec<Option<i64>> {
+    std::fs::read_to_string(path)
+        .unwrap_or_default()
+        .lines()
+        .map(|l| l.trim().parse().ok())
+        .collect()
+}
+
+fn report(results: &[CaseResult], best: &[Option<i64>]) {
+    const COLOR_RED: &str = "\x1b[31m";
+    const COLOR_BLUE: &str = "\x1b[34m";
+    const COLOR_RESET: &str = "\x1b[0m";
+
+    let mut total = 0;
+    // A, B, C, D の合計 (どの項目で悪化したかを見るため)
+    let mut components = [0; 4];
+    let mut relative_sum = 0.0;
+    let mut relative_cnt = 0;
+    let mut failures = 0;
+    for (i, r) in results.iter().enumerate() {
+        match &r.outcome {
+            Outcome::Score(details) => {
+                let score = &details.score;
+                total += score;
+                components[0] += details.turns;
+                components[1] += details.inversions;
+                components[2] += details.wrong_gate;
+                components[3] += details.undelivered;
+                let breakdown = format!(
+                    "A={} B={} C={} D={}",
+                    details.turns, details.inversions, details.wrong_gate, details.undelivered
+                );
+                // 最小化問題なので 100% 未満なら改善
+                match best.get(i).copied().flatten() {
+                    Some(b) if b > 0 => {
+                        let relative = *score as f64 / b as f64 * 100.0;
+                        relative_sum += relative;
+                        relative_cnt += 1;
+                        let color = if relative < 100.0 {
+                            COLOR_BLUE
+                        } else if relative > 100.0 {
+                            COLOR_RED
+                        } else {
+                            COLOR_RESET
+                        };
+                        println!(
+                            "{}: {:>8} ( {}{:.2}%{} ) [{}] {:.2}s",
+                            r.name, score, color, relative, COLOR_RESET, breakdown, r.elapsed
+                        );
+                    }
+                    _ => println!("{}: {:>8} (No best score) [{}] {:.2}s", r.name, score, breakdown, r.elapsed),
+                }
+            }
+            Outcome::Invalid(err) => {
+                failures += 1;
+                println!("{}: {}invalid output{}: {} {:.2}s", r.name, COLOR_RED, COLOR_RESET, err, r.elapsed);
+            }
+            Outcome::Failed(err) => {
+                failures += 1;
+                println!("{}: {}failed{}: {} {:.2}s", r.name, COLOR_RED, COLOR_RESET, err, r.elapsed);
+            }
+        }
+    }
+
+    let max_elapsed = results.iter().map(|r| r.elapsed).fold(0.0, f64::max);
+    let avg_elapsed = results.iter().map(|r| r.elapsed).sum::<f64>() / results.len() as f64;
+    println!("cases: {}, failures: {}", results.len(), failures);
+    println!("total score: {}", total);
+    println!(
+        "total A (turns): {}, B (inversions): {}, C (wrong gate): {}, D (undelivered): {}",
+        components[0], components[1], components[2], components[3]
+    );
+    if relative_cnt > 0 {
+        println!("average relative score: {:.2}%", relative_sum / relative_cnt as f64);
+    }
+    println!("elapsed: avg {:.2}s, max {:.2}s", avg_elapsed, max_elapsed);
+}
@@ -14,10 +14,20 @@ struct Cli {
     #[clap(short, long)]
     /// Print input details in csv format
     verbose: bool,
+    /// Size of the board and number of cranes
+    #[clap(short, long, default_value_t = 5)]
+    n: usize,
+    /// Arrangement of the containers
+    #[clap(short, long, value_enum, default_value_t = GenMode::Uniform)]
+    mode: GenMode,
 }
 
 fn main() {
     let cli = Cli::parse();
+    if !(MIN_N..=MAX_N).contains(&cli.n) {
+        eprintln!("n must be in {}..={}: {}", MIN_N, MAX_N, cli.n);
+        std::process::exit(1)
+    }
     if !std::path::Path::new(&cli.dir).exists() {
         std::fs::create_dir(&cli.dir).unwrap();
     }
@@ -28,7 +38,7 @@ fn main() {
     let f = std::io::BufReader::new(f);
     let mut id = 0;
     if cli.verbose {
-        println!("file,seed");
+        println!("file,seed,n,mode");
     }
     for line in f.lines() {
         let line = line.unwrap();
@@ -40,9 +50,9 @@ fn main() {
             eprintln!("parse failed: {}", line);
             std::process::exit(1)
         });
-        let input = gen(seed);
+        let input = gen(seed, cli.n, cli.mode);
         if cli.verbose {
-            println!("{:04},{}", id, seed);
+            println!("{:04},{},{},{}", id, seed, cli.n, cli.mode);
         }
         let mut w = std::io::BufWriter::new(std::fs::File::create(cli.dir.join(format!("{:04}.txt", id))).unwrap());
         write!(w, "{}", input).unwrap();
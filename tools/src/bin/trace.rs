@@ -0,0 +1,35 @@
+#![allow(non_snake_case)]
+
+use tools::*;
+
+fn main() {
+    if std::env::args().len() != 3 && std::env::args().len() != 4 {
+        eprintln!("Usage: {} <input> <output> [trace.jsonl]", std::env::args().next().unwrap());
+        return;
+    }
+    let in_file = std::env::args().nth(1).unwrap();
+    let out_file = std::env::args().nth(2).unwrap();
+    let input = std::fs::read_to_string(&in_file).unwrap_or_else(|_| {
+        eprintln!("no such file: {}", in_file);
+        std::process::exit(1)
+    });
+    let output = std::fs::read_to_string(&out_file).unwrap_or_else(|_| {
+        eprintln!("no such file: {}", out_file);
+        std::process::exit(1)
+    });
+    let input = parse_input(&input);
+    let out = parse_output(&input, &output);
+    let (score, err, trace) = match out {
+        Ok(out) => trace(&input, &out),
+        Err(err) => (0, err, String::new()),
+    };
+    // トレースを標準出力に流せるよう、スコアは標準エラーに出す
+    if !err.is_empty() {
+        eprintln!("{}", err);
+    }
+    eprintln!("Score = {}", score);
+    match std::env::args().nth(3) {
+        Some(path) => std::fs::write(path, &trace).unwrap(),
+        None => print!("{}", trace),
+    }
+}
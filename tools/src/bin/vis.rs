@@ -19,9 +19,14 @@ fn main() {
     });
     let input = parse_input(&input);
     let out = parse_output(&input, &output);
-    let (score, err, svg) = match out {
-        Ok(out) => vis_default(&input, &out),
-        Err(err) => (0, err, String::new()),
+    let (score, err, html) = match out {
+        Ok(out) => {
+            let t = out.out.iter().map(|s| s.len()).max().unwrap();
+            let (details, _, _) = compute_score_details(&input, &out, t);
+            println!("{}", details);
+            vis_animation(&input, &out)
+        }
+        Err(err) => (0, err.clone(), format!("<html><body>{}</body></html>", html_escape(&err))),
     };
     if err.len() > 0 {
         println!("{}", err);
@@ -29,6 +34,5 @@ fn main() {
     } else {
         println!("Score = {}", score);
     }
-    let vis = format!("<html><body>{}</body></html>", svg);
-    std::fs::write("vis.html", &vis).unwrap();
+    std::fs::write("vis.html", &html).unwrap();
 }
@@ -96,43 +96,154 @@ pub fn parse_output(input: &Input, f: &str) -> Result<Output, String> {
     Ok(Output { out })
 }
 
-pub fn gen(seed: u64) -> Input {
+/// How the containers are arranged in the carry-in queues.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum GenMode {
+    /// Uniformly random permutation of 0..N^2 (the official distribution)
+    Uniform,
+    /// Row i holds i*N..(i+1)*N in order, then a few nearby pairs are swapped
+    NearlySorted,
+    /// Uniformly random, then each row is sorted in decreasing order
+    ReverseSorted,
+    /// Each row holds exactly one container for every gate, in random order
+    Adversarial,
+}
+
+impl std::fmt::Display for GenMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use clap::ValueEnum;
+        write!(f, "{}", self.to_possible_value().unwrap().get_name())
+    }
+}
+
+pub const MIN_N: usize = 2;
+pub const MAX_N: usize = 10;
+
+pub fn gen(seed: u64, n: usize, mode: GenMode) -> Input {
+    assert!((MIN_N..=MAX_N).contains(&n), "n must be in {}..={}: {}", MIN_N, MAX_N, n);
     let mut rng = rand_chacha::ChaCha20Rng::seed_from_u64(seed);
-    let n = 5;
     let mut order = (0..n * n).collect_vec();
-    order.shuffle(&mut rng);
+    match mode {
+        GenMode::Uniform | GenMode::ReverseSorted => order.shuffle(&mut rng),
+        GenMode::NearlySorted => {
+            for _ in 0..n {
+                let i = rng.gen_range(0..n * n - 1);
+                let j = rng.gen_range(i + 1..(i + n).min(n * n - 1) + 1);
+                order.swap(i, j);
+            }
+        }
+        GenMode::Adversarial => {
+            // 搬出口 g 行きのコンテナ g * n..(g + 1) * n を各行に 1 つずつ配る
+            let mut rows = vec![vec![]; n];
+            for g in 0..n {
+                let mut perm = (0..n).collect_vec();
+                perm.shuffle(&mut rng);
+                for (row, p) in rows.iter_mut().zip(perm) {
+                    row.push(g * n + p);
+                }
+            }
+            order.clear();
+            for mut row in rows {
+                row.shuffle(&mut rng);
+                order.extend(row);
+            }
+        }
+    }
     let mut A = mat![0; n; n];
     for i in 0..n {
         for j in 0..n {
             A[i][j] = order[i * n + j] as i32;
         }
+        if mode == GenMode::ReverseSorted {
+            A[i].sort_by(|a, b| b.cmp(a));
+        }
     }
     Input { n, A }
 }
 
 pub fn compute_score(input: &Input, out: &Output) -> (i64, String) {
-    let (mut score, err, _) = compute_score_details(input, out, out.out.iter().map(|s| s.len()).max().unwrap());
-    if err.len() > 0 {
-        score = 0;
+    let (details, err, _) = compute_score_details(input, out, out.out.iter().map(|s| s.len()).max().unwrap());
+    (details.score, err)
+}
+
+/// Breakdown of the score `A + 100B + 10^4 C + 10^6 D`.
+#[derive(Clone, Debug, Default)]
+pub struct ScoreBreakdown {
+    /// Total score (0 if the output is invalid)
+    pub score: i64,
+    /// A: number of turns
+    pub turns: i64,
+    /// B: number of inversions among the containers dispatched from the correct gate
+    pub inversions: i64,
+    /// C: number of containers dispatched from a wrong gate
+    pub wrong_gate: i64,
+    /// D: number of containers not yet dispatched
+    pub undelivered: i64,
+    /// Inverted pairs `(earlier, later)` for each gate
+    pub inversion_pairs: Vec<Vec<(i32, i32)>>,
+    /// Containers dispatched from each gate that belong to another gate
+    pub wrong_gate_containers: Vec<Vec<i32>>,
+}
+
+impl std::fmt::Display for ScoreBreakdown {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "A = {} (turns), B = {} (inversions), C = {} (wrong gate), D = {} (undelivered)",
+            self.turns, self.inversions, self.wrong_gate, self.undelivered
+        )?;
+        for i in 0..self.inversion_pairs.len() {
+            if self.inversion_pairs[i].is_empty() && self.wrong_gate_containers[i].is_empty() {
+                continue;
+            }
+            write!(f, "\ngate {}:", i)?;
+            if !self.inversion_pairs[i].is_empty() {
+                write!(
+                    f,
+                    " inversions {}",
+                    self.inversion_pairs[i].iter().map(|(a, b)| format!("{a}>{b}")).join(" ")
+                )?;
+            }
+            if !self.wrong_gate_containers[i].is_empty() {
+                write!(f, " wrong gate {}", self.wrong_gate_containers[i].iter().join(" "))?;
+            }
+        }
+        Ok(())
     }
-    (score, err)
 }
 
 const DIJ: [(usize, usize); 4] = [(!0, 0), (1, 0), (0, !0), (0, 1)];
 const DIR: [char; 4] = ['U', 'D', 'L', 'R'];
 
+/// Judge simulator. `apply` follows the official rules, so other simulators can be checked against it.
+#[derive(Clone)]
 pub struct State {
     n: usize,
     board: Vec<Vec<i32>>,
     A: Vec<Vec<i32>>,
     B: Vec<Vec<i32>>,
+    wrong: Vec<Vec<i32>>,
     pos: Vec<(usize, usize, i32)>,
     done: i32,
     turn: i64,
 }
 
+/// What the judge sees after a turn, for comparing other simulators with it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Snapshot {
+    /// Container on the ground of each square (`-1` if empty)
+    pub board: Vec<Vec<i32>>,
+    /// `(x, y, hold)` of each crane (`x == y == !0` after it is bombed, `hold == -1` if it holds nothing)
+    pub cranes: Vec<(usize, usize, i32)>,
+    /// Containers dispatched so far from any gate, sorted
+    pub dispatched: Vec<i32>,
+    /// Containers dispatched so far from a wrong gate, sorted
+    pub wrong_gate: Vec<i32>,
+}
+
 impl State {
-    fn new(input: &Input) -> Self {
+    /// Initial state: the first container of each row is already on the board.
+    pub fn new(input: &Input) -> Self {
         let mut board = mat![-1; input.n; input.n];
         let mut A = input.A.iter().map(|a| a.iter().copied().rev().collect_vec()).collect_vec();
         for i in 0..input.n {
@@ -143,12 +254,14 @@ impl State {
             board,
             A,
             B: vec![vec![]; input.n],
+            wrong: vec![vec![]; input.n],
             pos: (0..input.n).map(|i| (i, 0, -1)).collect_vec(),
             done: 0,
             turn: 0,
         }
     }
-    fn apply(&mut self, mv: &[char]) -> Result<(), String> {
+    /// Apply one turn (`mv[i]` is the move of crane `i`). On error the state is left half-updated.
+    pub fn apply(&mut self, mv: &[char]) -> Result<(), String> {
         self.turn += 1;
         let mut to = vec![(!0, !0, -1); self.n];
         for i in 0..self.n {
@@ -235,6 +348,8 @@ impl State {
                 self.done += 1;
                 if (self.n * i) as i32 <= self.board[i][self.n - 1] && self.board[i][self.n - 1] < (self.n * (i + 1)) as i32 {
                     self.B[i].push(self.board[i][self.n - 1]);
+                } else {
+                    self.wrong[i].push(self.board[i][self.n - 1]);
                 }
                 self.board[i][self.n - 1] = -1;
             }
@@ -242,35 +357,140 @@ impl State {
         Ok(())
     }
     fn score(&self) -> i64 {
-        let A = self.turn;
-        let mut B = 0;
-        let mut C = self.done as i64;
-        let D = (self.n * self.n) as i64 - self.done as i64;
-        for i in 0..self.n {
-            C -= self.B[i].len() as i64;
-            for a in 0..self.B[i].len() {
-                for b in a + 1..self.B[i].len() {
-                    if self.B[i][a] > self.B[i][b] {
-                        B += 1;
+        self.breakdown().score
+    }
+    pub fn snapshot(&self) -> Snapshot {
+        let mut dispatched = self.B.iter().chain(self.wrong.iter()).flatten().copied().collect_vec();
+        dispatched.sort();
+        let mut wrong_gate = self.wrong.iter().flatten().copied().collect_vec();
+        wrong_gate.sort();
+        Snapshot {
+            board: self.board.clone(),
+            cranes: self.pos.clone(),
+            dispatched,
+            wrong_gate,
+        }
+    }
+    fn breakdown(&self) -> ScoreBreakdown {
+        let inversion_pairs = self
+            .B
+            .iter()
+            .map(|b| {
+                let mut pairs = vec![];
+                for x in 0..b.len() {
+                    for y in x + 1..b.len() {
+                        if b[x] > b[y] {
+                            pairs.push((b[x], b[y]));
+                        }
                     }
                 }
-            }
+                pairs
+            })
+            .collect_vec();
+        let A = self.turn;
+        let B = inversion_pairs.iter().map(|p| p.len() as i64).sum::<i64>();
+        let C = self.wrong.iter().map(|w| w.len() as i64).sum::<i64>();
+        let D = (self.n * self.n) as i64 - self.done as i64;
+        ScoreBreakdown {
+            score: A + B * 100 + C * 10000 + D * 1000000,
+            turns: A,
+            inversions: B,
+            wrong_gate: C,
+            undelivered: D,
+            inversion_pairs,
+            wrong_gate_containers: self.wrong.clone(),
         }
-        let score = A + B * 100 + C * 10000 + D * 1000000;
-        score
+    }
+    /// One JSON object describing the state after the moves `mv` of this turn.
+    fn to_json(&self, mv: &[char]) -> String {
+        let cell = |v: i32| if v == -1 { "null".to_owned() } else { v.to_string() };
+        let moves = mv.iter().map(|c| format!("\"{}\"", c)).join(",");
+        let cranes = (0..self.n)
+            .map(|i| {
+                let (x, y, z) = self.pos[i];
+                let pos = if x == !0 { "null".to_owned() } else { format!("[{},{}]", x, y) };
+                format!("{{\"id\":{},\"pos\":{},\"hold\":{}}}", i, pos, cell(z))
+            })
+            .join(",");
+        let board = self.board.iter().map(|row| format!("[{}]", row.iter().map(|&v| cell(v)).join(","))).join(",");
+        // A は末尾から搬入されるので、次に来るコンテナが先頭になるよう反転する
+        let incoming = self.A.iter().map(|a| format!("[{}]", a.iter().rev().join(","))).join(",");
+        let dispatched = self.B.iter().map(|b| format!("[{}]", b.iter().join(","))).join(",");
+        let wrong_gate = self.wrong.iter().map(|w| format!("[{}]", w.iter().join(","))).join(",");
+        let details = self.breakdown();
+        format!(
+            "{{\"turn\":{},\"moves\":[{}],\"cranes\":[{}],\"board\":[{}],\"incoming\":[{}],\"dispatched\":[{}],\"wrong_gate\":[{}],\"done\":{},\"score\":{{\"A\":{},\"B\":{},\"C\":{},\"D\":{},\"total\":{}}}}}",
+            self.turn,
+            moves,
+            cranes,
+            board,
+            incoming,
+            dispatched,
+            wrong_gate,
+            self.done,
+            details.turns,
+            details.inversions,
+            details.wrong_gate,
+            details.undelivered,
+            details.score
+        )
     }
 }
 
-pub fn compute_score_details(input: &Input, out: &Output, t: usize) -> (i64, String, State) {
+/// Simulate the first `t` turns. If a move is rejected, the breakdown describes the state
+/// at that point but its `score` is 0.
+pub fn compute_score_details(input: &Input, out: &Output, t: usize) -> (ScoreBreakdown, String, State) {
     let mut state = State::new(input);
     for k in 0..t {
         let mv = (0..input.n).map(|i| out.out[i].get(k).copied().unwrap_or('.')).collect_vec();
         if let Err(err) = state.apply(&mv) {
-            return (0, format!("{err} (turn {k})"), state);
+            let mut details = state.breakdown();
+            details.score = 0;
+            return (details, format!("{err} (turn {k})"), state);
         }
     }
-    let score = state.score();
-    (score, String::new(), state)
+    (state.breakdown(), String::new(), state)
+}
+
+/// Simulate `out` and return a JSON Lines trace with one record per turn (turn 0 is the initial state).
+/// If a move is rejected, the last record is `{"turn":k,"error":...}` for the offending turn.
+pub fn trace(input: &Input, out: &Output) -> (i64, String, String) {
+    let T = out.out.iter().map(|s| s.len()).max().unwrap();
+    let mut state = State::new(input);
+    let mut lines = vec![state.to_json(&[])];
+    for k in 0..T {
+        let mv = (0..input.n).map(|i| out.out[i].get(k).copied().unwrap_or('.')).collect_vec();
+        if let Err(err) = state.apply(&mv) {
+            let err = format!("{err} (turn {k})");
+            lines.push(format!("{{\"turn\":{},\"error\":{}}}", k + 1, json_string(&err)));
+            return (0, err, lines.join("\n") + "\n");
+        }
+        lines.push(state.to_json(&mv));
+    }
+    (state.score(), String::new(), lines.join("\n") + "\n")
+}
+
+/// Escape `s` so that it can be embedded as text in an HTML page.
+pub fn html_escape(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;")
+}
+
+/// Quote `s` as a JSON string literal. `<` is escaped as well so that it is safe to embed in a `<script>` element.
+fn json_string(s: &str) -> String {
+    let mut ret = String::with_capacity(s.len() + 2);
+    ret.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => ret.push_str("\\\""),
+            '\\' => ret.push_str("\\\\"),
+            '\n' => ret.push_str("\\n"),
+            '<' => ret.push_str("\\u003c"),
+            c if (c as u32) < 0x20 => ret.push_str(&format!("\\u{:04x}", c as u32)),
+            _ => ret.push(c),
+        }
+    }
+    ret.push('"');
+    ret
 }
 
 /// 0 <= val <= 1
@@ -308,14 +528,6 @@ pub fn group(title: String) -> Group {
     Group::new().add(Title::new(title))
 }
 
-pub fn vis_default(input: &Input, out: &Output) -> (i64, String, String) {
-    let (mut score, err, svg) = vis(input, &out, out.out.iter().map(|s| s.len()).max().unwrap());
-    if err.len() > 0 {
-        score = 0;
-    }
-    (score, err, svg)
-}
-
 // https://www.svgrepo.com/svg/175680/crane
 pub const CRANE: &'static str = r#"<path d="m396.1,11h-238.9c-7.5-0.5-22.1,6.8-20.5,22.3l19.9,211.2c1,10.5 9.9,18.5 20.5,18.5h79v46.9c0,11.3 9.2,20.4 20.5,20.4 36,0 65.3,29.1 65.3,64.9s-29.3,64.9-65.3,64.9c-36,0-65.3-29.1-65.3-64.9 0-11.3-9.2-20.4-20.5-20.4-11.3,0-20.5,9.1-20.5,20.4 0,58.3 47.7,105.7 106.4,105.7 58.7,0 106.4-47.4 106.4-105.7 0-51.3-37-94.2-85.9-103.8v-28.4h79c10.6,0 19.5-8 20.5-18.5l19.9-211.2c0.8-6.8-3.2-21.6-20.5-22.3zm-38.6,211.2h-161.7l-16-170.4h193.8l-16.1,170.4z"/>"#;
 
@@ -324,7 +536,8 @@ pub fn vis(input: &Input, out: &Output, t: usize) -> (i64, String, String) {
     let D = 600 / (input.n + 2);
     let W = D * (input.n + 2) + D / input.n;
     let H = D * input.n + S;
-    let (score, err, state) = compute_score_details(input, out, t);
+    let (details, err, state) = compute_score_details(input, out, t);
+    let score = details.score;
     let mut doc = svg::Document::new()
         .set("id", "vis")
         .set("viewBox", (-5, -5, W + 10, H + 10))
@@ -478,3 +691,103 @@ pub fn vis(input: &Input, out: &Output, t: usize) -> (i64, String, String) {
     }
     (score, err, doc.to_string())
 }
+
+/// Render every turn with `vis` and bundle the frames into a self-contained HTML page
+/// with a slider, play/pause, speed control and step buttons.
+pub fn vis_animation(input: &Input, out: &Output) -> (i64, String, String) {
+    let T = out.out.iter().map(|s| s.len()).max().unwrap();
+    let (score, err) = compute_score(input, out);
+    let mut frames = vec![];
+    let mut scores = vec![];
+    for t in 0..=T {
+        let (s, e, svg) = vis(input, out, t);
+        frames.push(svg);
+        scores.push(s);
+        // 不正な操作があったターン以降は同じ盤面になるので打ち切る
+        if !e.is_empty() {
+            break;
+        }
+    }
+    let frames = format!("[{}]", frames.iter().map(|f| json_string(f)).join(","));
+    let scores = format!("[{}]", scores.iter().join(","));
+    let html = format!(
+        r#"<html>
+<head>
+<meta charset="utf-8">
+<style>
+body {{ font-family: sans-serif; }}
+#controls {{ display: flex; align-items: center; gap: 8px; margin-bottom: 8px; }}
+#turn {{ width: 400px; }}
+#err {{ color: red; }}
+</style>
+</head>
+<body>
+<div id="controls">
+<button id="first">|&lt;</button>
+<button id="prev">&lt;</button>
+<button id="play">play</button>
+<button id="next">&gt;</button>
+<button id="last">&gt;|</button>
+<input type="range" id="turn" min="0" max="{max}" value="{max}">
+<span id="label"></span>
+<label>speed <select id="speed">
+<option value="1">1 fps</option>
+<option value="5">5 fps</option>
+<option value="10" selected>10 fps</option>
+<option value="30">30 fps</option>
+<option value="60">60 fps</option>
+</select></label>
+</div>
+<div id="err">{err}</div>
+<div id="frame"></div>
+<script>
+const frames = {frames};
+const scores = {scores};
+const slider = document.getElementById("turn");
+const playButton = document.getElementById("play");
+let timer = null;
+slider.max = frames.length - 1;
+function show(t) {{
+    t = Math.max(0, Math.min(frames.length - 1, t));
+    slider.value = t;
+    document.getElementById("frame").innerHTML = frames[t];
+    document.getElementById("label").textContent = "turn " + t + " / " + (frames.length - 1) + ", score = " + scores[t];
+}}
+function stop() {{
+    clearInterval(timer);
+    timer = null;
+    playButton.textContent = "play";
+}}
+function start() {{
+    if (Number(slider.value) >= frames.length - 1) show(0);
+    playButton.textContent = "pause";
+    timer = setInterval(() => {{
+        const t = Number(slider.value) + 1;
+        show(t);
+        if (t >= frames.length - 1) stop();
+    }}, 1000 / Number(document.getElementById("speed").value));
+}}
+playButton.onclick = () => timer === null ? start() : stop();
+document.getElementById("speed").onchange = () => {{ if (timer !== null) {{ stop(); start(); }} }};
+document.getElementById("first").onclick = () => {{ stop(); show(0); }};
+document.getElementById("prev").onclick = () => {{ stop(); show(Number(slider.value) - 1); }};
+document.getElementById("next").onclick = () => {{ stop(); show(Number(slider.value) + 1); }};
+document.getElementById("last").onclick = () => {{ stop(); show(frames.length - 1); }};
+slider.oninput = () => {{ stop(); show(Number(slider.value)); }};
+document.addEventListener("keydown", (e) => {{
+    if (e.key === "ArrowLeft") {{ stop(); show(Number(slider.value) - 1); }}
+    else if (e.key === "ArrowRight") {{ stop(); show(Number(slider.value) + 1); }}
+    else if (e.key === " ") {{ e.preventDefault(); timer === null ? start() : stop(); }}
+}});
+show(frames.length - 1);
+</script>
+</body>
+</html>
+"#,
+        max = T,
+        err = html_escape(&err),
+        frames = frames,
+        scores = scores,
+    );
+    (score, err, html)
+}